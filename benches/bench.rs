@@ -125,6 +125,21 @@ fn iterate_100k(b: &mut Bencher) {
     })
 }
 
+fn iterate_for_each_100k(b: &mut Bencher) {
+    let mut world = World::new();
+    for i in 0..100_000 {
+        world.spawn((Position(-(i as f32)), Velocity(i as f32)));
+    }
+    b.iter(|| {
+        world
+            .query::<(&mut Position, &Velocity)>()
+            .iter()
+            .for_each(|(_, (pos, vel))| {
+                pos.0 += vel.0;
+            })
+    })
+}
+
 fn iterate_mut_100k(b: &mut Bencher) {
     let mut world = World::new();
     for i in 0..100_000 {
@@ -194,6 +209,19 @@ fn iterate_cached_100_by_50(b: &mut Bencher) {
     })
 }
 
+fn iterate_for_each_uncached_100_by_50(b: &mut Bencher) {
+    let mut world = World::new();
+    spawn_100_by_50(&mut world);
+    b.iter(|| {
+        world
+            .query::<(&mut Position, &Velocity)>()
+            .iter()
+            .for_each(|(_, (pos, vel))| {
+                pos.0 += vel.0;
+            })
+    })
+}
+
 fn iterate_mut_uncached_100_by_50(b: &mut Bencher) {
     let mut world = World::new();
     spawn_100_by_50(&mut world);
@@ -269,9 +297,11 @@ benchmark_group!(
     insert_remove,
     exchange,
     iterate_100k,
+    iterate_for_each_100k,
     iterate_mut_100k,
     iterate_uncached_100_by_50,
     iterate_cached_100_by_50,
+    iterate_for_each_uncached_100_by_50,
     iterate_mut_uncached_100_by_50,
     iterate_mut_cached_100_by_50,
     build,
@@ -10,6 +10,7 @@ extern crate proc_macro;
 mod bundle;
 mod bundle_clone;
 mod query;
+mod transparent;
 
 pub(crate) mod common;
 
@@ -95,3 +96,30 @@ pub fn derive_query(input: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+/// Implement `Transparent` for a `#[repr(transparent)]` struct with exactly one field
+///
+/// Allows the struct's field to be accessed directly with the `Unwrap` query.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Transparent)]
+/// #[repr(transparent)]
+/// struct Health(f32);
+///
+/// let mut world = World::new();
+/// let e = world.spawn((Health(10.0),));
+/// for (_, health) in world.query_mut::<Unwrap<Health>>() {
+///     *health -= 1.0;
+/// }
+/// assert_eq!(world.get::<Health>(e).unwrap().0, 9.0);
+/// ```
+#[proc_macro_derive(Transparent)]
+pub fn derive_transparent(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match transparent::derive(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error(),
+    }
+    .into()
+}
@@ -168,6 +168,13 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
                 )*
             }
 
+            #[allow(unused_variables, unused_mut)]
+            fn for_each_filter(mut f: impl ::core::ops::FnMut(::core::any::TypeId, bool)) {
+                #(
+                    <#fetches as ::hecs::Fetch<'static>>::for_each_filter(&mut f);
+                )*
+            }
+
             #[allow(unused_variables)]
             unsafe fn get(&self, n: usize) -> Self::Item {
                 #ident {
@@ -0,0 +1,54 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{DeriveInput, Error, Meta, NestedMeta, Result};
+
+use crate::common::struct_fields;
+
+pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = input.ident;
+    let data = match input.data {
+        syn::Data::Struct(s) => s,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "derive(Transparent) may only be applied to structs",
+            ))
+        }
+    };
+    if !is_repr_transparent(&input.attrs) {
+        return Err(Error::new_spanned(
+            ident,
+            "derive(Transparent) requires #[repr(transparent)]",
+        ));
+    }
+    let (tys, _) = struct_fields(&data.fields);
+    let inner = match tys[..] {
+        [ty] => ty,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "derive(Transparent) requires exactly one field",
+            ))
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        unsafe impl #impl_generics ::hecs::Transparent for #ident #ty_generics #where_clause {
+            type Inner = #inner;
+        }
+    })
+}
+
+fn is_repr_transparent(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            Meta::List(list) => list.nested.iter().any(|x| match x {
+                NestedMeta::Meta(Meta::Path(path)) => path.is_ident("transparent"),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
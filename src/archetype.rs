@@ -150,6 +150,24 @@ impl Archetype {
         self.data[state].state.release_mut();
     }
 
+    /// Index of the column of type `ty`, for use with `borrow_raw` and `release_raw`
+    pub(crate) fn get_dynamic_state(&self, ty: TypeId) -> Option<usize> {
+        self.index.get(&ty).copied()
+    }
+
+    pub(crate) fn borrow_raw(&self, state: usize) {
+        if !self.data[state].state.borrow() {
+            #[cfg(debug_assertions)]
+            panic!("{} already borrowed uniquely", self.types[state].type_name);
+            #[cfg(not(debug_assertions))]
+            panic!("component already borrowed uniquely");
+        }
+    }
+
+    pub(crate) fn release_raw(&self, state: usize) {
+        self.data[state].state.release();
+    }
+
     /// Number of entities in this archetype
     #[inline]
     pub fn len(&self) -> u32 {
@@ -220,6 +238,23 @@ impl Archetype {
         ))
     }
 
+    /// Panic if any component in this archetype has an outstanding borrow
+    pub(crate) fn assert_no_borrows(&self) {
+        #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+        if let Some(state) = self.data.iter().position(|data| !data.state.is_free()) {
+            #[cfg(debug_assertions)]
+            panic!("{} is still borrowed", self.types[state].type_name);
+            #[cfg(not(debug_assertions))]
+            panic!("a component is still borrowed");
+        }
+    }
+
+    /// Address of the first element and size of each element of the column of type `ty`
+    pub(crate) fn column_base(&self, ty: TypeId) -> Option<(NonNull<u8>, usize)> {
+        let index = *self.index.get(&ty)?;
+        Some((self.data[index].storage, self.types[index].layout.size()))
+    }
+
     /// Every type must be written immediately after this call
     pub(crate) unsafe fn allocate(&mut self, id: u32) -> u32 {
         if self.len as usize == self.entities.len() {
@@ -255,6 +290,12 @@ impl Archetype {
 
     /// Increase capacity by exactly `increment`
     fn grow_exact(&mut self, increment: u32) {
+        // `&mut self` should rule out live borrows, but leaked guards or misuse of the unsafe
+        // internal APIs could leave outstanding references into the storage we're about to free.
+        debug_assert!(
+            self.data.iter().all(|x| x.state.is_free()),
+            "archetype reallocated while a component was borrowed"
+        );
         let old_count = self.len as usize;
         let old_cap = self.entities.len();
         let new_cap = self.entities.len() + increment as usize;
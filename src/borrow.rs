@@ -64,6 +64,11 @@ impl AtomicBorrow {
         let value = self.0.fetch_and(!UNIQUE_BIT, Ordering::Release);
         debug_assert_ne!(value & UNIQUE_BIT, 0, "unique release of shared borrow");
     }
+
+    /// Whether no borrow of any kind is currently outstanding
+    pub fn is_free(&self) -> bool {
+        self.0.load(Ordering::Relaxed) == 0
+    }
 }
 
 #[cfg(test)]
@@ -97,5 +102,8 @@ mod tests {
         assert!(!counter.borrow());
         counter.release_mut();
         assert!(counter.borrow());
+        assert!(!counter.is_free());
+        counter.release();
+        assert!(counter.is_free());
     }
 }
@@ -19,6 +19,10 @@ use crate::{Bundle, Entity};
 ///
 /// Useful when operations cannot be applied directly due to ordering concerns or borrow checking.
 ///
+/// Because [`run_on`](Self::run_on) takes `&mut World`, recorded commands can only ever be applied
+/// between queries, never while one is being iterated. Each query therefore sees every entity at
+/// most once, in a single consistent archetype layout.
+///
 /// ```
 /// # use hecs::*;
 /// let mut world = World::new();
@@ -80,6 +84,31 @@ impl CommandBuffer {
     /// Add components from `bundle` to `entity`, if it exists
     ///
     /// Pairs well with [`World::reserve_entity`] to spawn entities with a known handle.
+    ///
+    /// Like [`World::insert`], any existing components of the same types are replaced. This makes
+    /// a command buffer a safe way to write to other entities while iterating a query that might
+    /// also visit them: writes are queued, and become visible only once the buffer is run after
+    /// iteration completes.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Target(Entity);
+    /// struct Force(f32);
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((Force(0.0),));
+    /// let b = world.spawn((Force(0.0), Target(a)));
+    /// world.insert_one(a, Target(b)).unwrap();
+    ///
+    /// let mut cmd = CommandBuffer::new();
+    /// for (_, (target, force)) in world.query::<(&Target, &Force)>().iter() {
+    ///     cmd.insert(target.0, (Force(force.0 + 1.0),));
+    /// }
+    /// cmd.run_on(&mut world);
+    /// assert_eq!(world.get::<Force>(a).unwrap().0, 1.0);
+    /// assert_eq!(world.get::<Force>(b).unwrap().0, 1.0);
+    /// ```
     pub fn insert(&mut self, entity: Entity, components: impl DynamicBundle) {
         let first_component = self.components.len();
         unsafe {
@@ -69,6 +69,16 @@ impl Entity {
     pub fn id(self) -> u32 {
         self.id
     }
+
+    /// Extract the generation of the entity's ID
+    ///
+    /// Incremented each time an ID is reused, so a handle's `id` and `generation` together identify
+    /// exactly one incarnation of an entity. Since queries yield the current handle of each entity,
+    /// this can be logged to tell which incarnation was processed when diagnosing stale cached
+    /// handles.
+    pub fn generation(self) -> NonZeroU32 {
+        self.generation
+    }
 }
 
 impl fmt::Debug for Entity {
@@ -185,6 +195,9 @@ pub(crate) struct Entities {
     pending: Vec<u32>,
     free_cursor: AtomicIsize,
     len: u32,
+
+    /// Allocated entities in allocation order, if tracked
+    spawn_order: Option<SpawnOrder>,
 }
 
 impl Entities {
@@ -272,7 +285,7 @@ impl Entities {
         self.verify_flushed();
 
         self.len += 1;
-        if let Some(id) = self.pending.pop() {
+        let entity = if let Some(id) = self.pending.pop() {
             let new_free_cursor = self.pending.len() as isize;
             self.free_cursor.store(new_free_cursor, Ordering::Relaxed); // Not racey due to &mut self
             Entity {
@@ -286,7 +299,9 @@ impl Entities {
                 generation: NonZeroU32::new(1).unwrap(),
                 id,
             }
-        }
+        };
+        self.record_spawn(entity.id);
+        entity
     }
 
     /// Allocate and set locations for many entity IDs laid out contiguously in an archetype
@@ -307,6 +322,12 @@ impl Entities {
                 index: first_index,
             };
             first_index += 1;
+            if let Some(ref mut order) = self.spawn_order {
+                order.record(Entity {
+                    generation: self.meta[id as usize].generation,
+                    id,
+                });
+            }
         }
 
         let fresh_start = self.meta.len() as u32;
@@ -318,6 +339,14 @@ impl Entities {
         );
 
         self.len += n;
+        if let Some(ref mut order) = self.spawn_order {
+            for id in fresh_start..(fresh_start + fresh) {
+                order.record(Entity {
+                    generation: NonZeroU32::new(1).unwrap(),
+                    id,
+                });
+            }
+        }
 
         AllocManyState {
             fresh: fresh_start..(fresh_start + fresh),
@@ -359,6 +388,7 @@ impl Entities {
         };
 
         self.meta[entity.id as usize].generation = entity.generation;
+        self.record_spawn(entity.id);
 
         loc
     }
@@ -385,6 +415,10 @@ impl Entities {
         self.free_cursor.store(new_free_cursor, Ordering::Relaxed); // Not racey due to &mut self
         self.len -= 1;
 
+        if let Some(ref mut order) = self.spawn_order {
+            order.forget(entity.id);
+        }
+
         Ok(loc)
     }
 
@@ -419,6 +453,33 @@ impl Entities {
         self.pending.clear();
         self.free_cursor.store(0, Ordering::Relaxed); // Not racey due to &mut self
         self.len = 0;
+        if let Some(ref mut order) = self.spawn_order {
+            *order = SpawnOrder::default();
+        }
+    }
+
+    /// Begin or stop recording the order in which entities are allocated
+    pub fn track_spawn_order(&mut self, enabled: bool) {
+        if !enabled {
+            self.spawn_order = None;
+        } else if self.spawn_order.is_none() {
+            self.spawn_order = Some(SpawnOrder::default());
+        }
+    }
+
+    /// Live entities allocated since tracking began, in allocation order
+    pub fn spawn_order(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.spawn_order.iter().flat_map(SpawnOrder::iter)
+    }
+
+    /// Record that the entity with ID `id` and its current generation was just allocated
+    fn record_spawn(&mut self, id: u32) {
+        if let Some(ref mut order) = self.spawn_order {
+            order.record(Entity {
+                generation: self.meta[id as usize].generation,
+                id,
+            });
+        }
     }
 
     /// Access the location storage of an entity
@@ -500,6 +561,9 @@ impl Entities {
             for (id, meta) in self.meta.iter_mut().enumerate().skip(old_meta_len) {
                 init(id as u32, &mut meta.location);
             }
+            for id in old_meta_len..new_meta_len {
+                self.record_spawn(id as u32);
+            }
 
             self.free_cursor.store(0, Ordering::Relaxed);
             0
@@ -508,6 +572,12 @@ impl Entities {
         self.len += (self.pending.len() - new_free_cursor) as u32;
         for id in self.pending.drain(new_free_cursor..) {
             init(id, &mut self.meta[id as usize].location);
+            if let Some(ref mut order) = self.spawn_order {
+                order.record(Entity {
+                    generation: self.meta[id as usize].generation,
+                    id,
+                });
+            }
         }
     }
 
@@ -517,6 +587,59 @@ impl Entities {
     }
 }
 
+/// Entities in the order they were allocated, with entries for freed or replaced IDs skipped
+#[derive(Default)]
+struct SpawnOrder {
+    order: Vec<Entity>,
+    /// Index in `order` of the live entry for each ID, or `u32::MAX` if none
+    position: Vec<u32>,
+    /// Number of entries in `order` that are no longer live
+    dead: usize,
+}
+
+impl SpawnOrder {
+    fn record(&mut self, entity: Entity) {
+        // An entry may survive for an ID that was restored with `alloc_at`
+        self.forget(entity.id);
+        let id = entity.id as usize;
+        if id >= self.position.len() {
+            self.position.resize(id + 1, u32::MAX);
+        }
+        self.position[id] = self.order.len() as u32;
+        self.order.push(entity);
+    }
+
+    fn forget(&mut self, id: u32) {
+        match self.position.get_mut(id as usize) {
+            Some(position) if *position != u32::MAX => *position = u32::MAX,
+            _ => return,
+        }
+        self.dead += 1;
+        // Compact once most entries are dead, amortizing the cost over many frees
+        if self.dead * 2 > self.order.len() {
+            let mut live = 0;
+            for i in 0..self.order.len() {
+                let entity = self.order[i];
+                if self.position[entity.id as usize] == i as u32 {
+                    self.order[live] = entity;
+                    self.position[entity.id as usize] = live as u32;
+                    live += 1;
+                }
+            }
+            self.order.truncate(live);
+            self.dead = 0;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.order
+            .iter()
+            .enumerate()
+            .filter(move |&(i, x)| self.position[x.id as usize] == i as u32)
+            .map(|(_, &x)| x)
+    }
+}
+
 #[derive(Copy, Clone)]
 pub(crate) struct EntityMeta {
     pub generation: NonZeroU32,
@@ -592,6 +715,53 @@ mod tests {
         assert_eq!(Entity::from_bits(e.to_bits().into()).unwrap(), e);
     }
 
+    #[test]
+    fn entity_bits_boundaries() {
+        let max = Entity {
+            generation: NonZeroU32::new(u32::MAX).unwrap(),
+            id: u32::MAX,
+        };
+        assert_eq!(max.to_bits().get(), u64::MAX);
+        assert_eq!(Entity::from_bits(u64::MAX), Some(max));
+        // The low half is the ID and the high half the nonzero generation
+        assert_eq!(Entity::from_bits(u64::from(u32::MAX)), None);
+        assert_eq!(
+            Entity::from_bits(1 << 32),
+            Some(Entity {
+                generation: NonZeroU32::new(1).unwrap(),
+                id: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn generation_advances_on_reuse() {
+        let mut e = Entities::default();
+        let first = e.alloc();
+        e.free(first).unwrap();
+        let second = e.alloc();
+        assert_eq!(first.id(), second.id());
+        assert_eq!(second.generation().get(), first.generation().get() + 1);
+    }
+
+    #[test]
+    fn generation_wraps() {
+        let mut e = Entities::default();
+        let entity = e.alloc();
+        e.free(entity).unwrap();
+        let entity = Entity {
+            generation: NonZeroU32::new(u32::MAX).unwrap(),
+            id: entity.id,
+        };
+        assert!(e.alloc_at(entity).is_none());
+        assert!(e.contains(entity));
+        e.free(entity).unwrap();
+        let next = e.alloc();
+        assert_eq!(next.id, entity.id);
+        assert_eq!(next.generation.get(), 1);
+        assert!(!e.contains(entity));
+    }
+
     #[test]
     fn alloc_and_free() {
         let mut rng = StdRng::seed_from_u64(0xFEEDFACEDEADF00D);
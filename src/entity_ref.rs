@@ -33,6 +33,11 @@ impl<'a> EntityRef<'a> {
         self.archetype.has::<T>()
     }
 
+    /// Determine whether this entity would satisfy the query `Q` without borrowing any components
+    pub fn satisfies<Q: Query>(&self) -> bool {
+        self.archetype.access::<Q>().is_some()
+    }
+
     /// Borrow the component of type `T`, if it exists
     ///
     /// Panics if the component is already uniquely borrowed from another entity with the same
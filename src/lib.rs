@@ -81,15 +81,16 @@ pub use entities::{Entity, NoSuchEntity};
 pub use entity_builder::{BuiltEntity, BuiltEntityClone, EntityBuilder, EntityBuilderClone};
 pub use entity_ref::{EntityRef, Ref, RefMut};
 pub use query::{
-    Access, Batch, BatchedIter, Or, PreparedQuery, PreparedQueryBorrow, PreparedQueryIter,
-    PreparedView, Query, QueryBorrow, QueryItem, QueryIter, QueryMut, QueryShared, Satisfies, View,
-    With, Without,
+    Access, Batch, BatchedIter, CowGuard, CowMut, DynamicQueryBorrow, DynamicQueryIter,
+    FilterFnIter, Or, PreparedQuery, PreparedQueryBorrow, PreparedQueryIter, PreparedView, Query,
+    QueryAccess, QueryBorrow, QueryExplanation, QueryItem, QueryIter, QueryMut, QueryShared,
+    Satisfies, Transparent, Unwrap, View, ViewAs, With, Without,
 };
 pub use query_one::QueryOne;
 pub use take::TakenEntity;
 pub use world::{
-    ArchetypesGeneration, Component, ComponentError, Iter, QueryOneError, SpawnBatchIter,
-    SpawnColumnBatchIter, World,
+    ArchetypeEvent, ArchetypesGeneration, Component, ComponentError, Dead, DrainArchetypeEvents,
+    InsertError, Iter, QueryOneError, SpawnBatchIter, SpawnColumnBatchIter, World,
 };
 
 // Unstable implementation details needed by the macros
@@ -104,7 +105,7 @@ pub use lazy_static;
 pub use query::Fetch;
 
 #[cfg(feature = "macros")]
-pub use hecs_macros::{Bundle, DynamicBundleClone, Query};
+pub use hecs_macros::{Bundle, DynamicBundleClone, Query, Transparent};
 
 fn align(x: usize, alignment: usize) -> usize {
     debug_assert!(alignment.is_power_of_two());
@@ -7,11 +7,11 @@
 
 use core::any::TypeId;
 use core::marker::PhantomData;
-use core::mem;
+use core::mem::{self, MaybeUninit};
 use core::ptr::NonNull;
 use core::slice::Iter as SliceIter;
 
-use crate::alloc::{boxed::Box, vec::Vec};
+use crate::alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
 use crate::archetype::Archetype;
 use crate::entities::EntityMeta;
 use crate::{Component, Entity, World};
@@ -61,6 +61,10 @@ pub unsafe trait Fetch<'a>: Sized {
     /// Invoke `f` for every component type that may be borrowed and whether the borrow is unique
     fn for_each_borrow(f: impl FnMut(TypeId, bool));
 
+    /// Invoke `f` for every component type that an archetype must have (`true`) or lack (`false`)
+    /// to match, without being borrowed
+    fn for_each_filter(_: impl FnMut(TypeId, bool)) {}
+
     /// Access the `n`th item in this archetype without bounds checking
     ///
     /// # Safety
@@ -82,6 +86,69 @@ pub enum Access {
     Write,
 }
 
+/// The component types a [`Query`] may read or write
+///
+/// Lets a scheduler decide which queries can run concurrently without running them. This is
+/// conservative: two queries whose accesses conflict may still never touch the same entity, in
+/// which case running them simultaneously would succeed.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use std::any::TypeId;
+/// let access = QueryAccess::of::<(&i32, Option<&mut bool>, With<&str, ()>)>();
+/// assert_eq!(access.reads(), [TypeId::of::<i32>()]);
+/// assert_eq!(access.writes(), [TypeId::of::<bool>()]);
+/// assert!(access.conflicts(&QueryAccess::of::<&bool>()));
+/// assert!(!access.conflicts(&QueryAccess::of::<&i32>()));
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct QueryAccess {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+}
+
+impl QueryAccess {
+    /// Compute the access of `Q`
+    pub fn of<Q: Query>() -> Self {
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+        Q::Fetch::for_each_borrow(|id, unique| {
+            if unique {
+                writes.push(id);
+            } else {
+                reads.push(id);
+            }
+        });
+        writes.sort_unstable();
+        writes.dedup();
+        reads.sort_unstable();
+        reads.dedup();
+        reads.retain(|id| writes.binary_search(id).is_err());
+        Self { reads, writes }
+    }
+
+    /// Sorted types that may be borrowed immutably, excluding those in [`writes`](Self::writes)
+    pub fn reads(&self) -> &[TypeId] {
+        &self.reads
+    }
+
+    /// Sorted types that may be borrowed uniquely
+    pub fn writes(&self) -> &[TypeId] {
+        &self.writes
+    }
+
+    /// Whether either access writes a type that the other reads or writes
+    pub fn conflicts(&self, other: &Self) -> bool {
+        fn writes_any(writes: &[TypeId], other: &QueryAccess) -> bool {
+            writes.iter().any(|id| {
+                other.reads.binary_search(id).is_ok() || other.writes.binary_search(id).is_ok()
+            })
+        }
+        writes_any(&self.writes, other) || writes_any(&other.writes, self)
+    }
+}
+
 impl<'a, T: Component> Query for &'a T {
     type Fetch = FetchRead<T>;
 }
@@ -430,6 +497,11 @@ unsafe impl<'a, T: Component, F: Fetch<'a>> Fetch<'a> for FetchWithout<T, F> {
         F::for_each_borrow(f);
     }
 
+    fn for_each_filter(mut f: impl FnMut(TypeId, bool)) {
+        F::for_each_filter(&mut f);
+        f(TypeId::of::<T>(), false);
+    }
+
     unsafe fn get(&self, n: usize) -> F::Item {
         self.0.get(n)
     }
@@ -502,6 +574,11 @@ unsafe impl<'a, T: Component, F: Fetch<'a>> Fetch<'a> for FetchWith<T, F> {
         F::for_each_borrow(f);
     }
 
+    fn for_each_filter(mut f: impl FnMut(TypeId, bool)) {
+        F::for_each_filter(&mut f);
+        f(TypeId::of::<T>(), true);
+    }
+
     unsafe fn get(&self, n: usize) -> F::Item {
         self.0.get(n)
     }
@@ -567,9 +644,291 @@ unsafe impl<'a, F: Fetch<'a>> Fetch<'a> for FetchSatisfies<F> {
     }
 }
 
+/// A query that reads `Old` components and yields them converted into `New` values
+///
+/// The conversion is registered by implementing `From<&Old> for New`, and runs once per matching
+/// entity, so it should be cheap. Useful for reading components stored in an obsolete format
+/// through their replacement during an incremental migration.
+///
+/// Yields owned `New` values rather than references; writes to them do not affect the world.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// struct PositionV1 { x: f32 }
+/// #[derive(Debug, PartialEq)]
+/// struct PositionV2 { x: f32, y: f32 }
+///
+/// impl From<&PositionV1> for PositionV2 {
+///     fn from(old: &PositionV1) -> Self {
+///         Self { x: old.x, y: 0.0 }
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let a = world.spawn((PositionV1 { x: 1.0 },));
+/// let positions = world.query::<ViewAs<PositionV1, PositionV2>>()
+///     .iter()
+///     .collect::<Vec<_>>();
+/// assert_eq!(positions, &[(a, PositionV2 { x: 1.0, y: 0.0 })]);
+/// ```
+pub struct ViewAs<Old, New>(PhantomData<fn(&Old) -> New>);
+
+impl<Old: Component, New: for<'x> From<&'x Old>> Query for ViewAs<Old, New> {
+    type Fetch = FetchViewAs<Old, New>;
+}
+
+unsafe impl<Old, New> QueryShared for ViewAs<Old, New> {}
+
+#[doc(hidden)]
+pub struct FetchViewAs<Old, New>(NonNull<Old>, PhantomData<fn() -> New>);
+
+unsafe impl<'a, Old: Component, New: for<'x> From<&'x Old>> Fetch<'a> for FetchViewAs<Old, New> {
+    type Item = New;
+
+    type State = usize;
+
+    fn dangling() -> Self {
+        Self(NonNull::dangling(), PhantomData)
+    }
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        if archetype.has::<Old>() {
+            Some(Access::Read)
+        } else {
+            None
+        }
+    }
+
+    fn borrow(archetype: &Archetype, state: Self::State) {
+        archetype.borrow::<Old>(state);
+    }
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        archetype.get_state::<Old>()
+    }
+    fn execute(archetype: &'a Archetype, state: Self::State) -> Self {
+        Self(archetype.get_base(state), PhantomData)
+    }
+    fn release(archetype: &Archetype, state: Self::State) {
+        archetype.release::<Old>(state);
+    }
+
+    fn for_each_borrow(mut f: impl FnMut(TypeId, bool)) {
+        f(TypeId::of::<Old>(), false);
+    }
+
+    unsafe fn get(&self, n: usize) -> New {
+        New::from(&*self.0.as_ptr().add(n))
+    }
+}
+
+/// Query that yields copy-on-write access to `Arc<T>` components
+///
+/// Yields a [`CowGuard`] for each entity with an `Arc<T>` component. Reading through the guard is
+/// as cheap as reading through the `Arc`; the first mutable access calls [`Arc::make_mut`],
+/// cloning the `T` if it's shared with any other `Arc`. Useful for data such as meshes that is
+/// mostly shared between many entities but occasionally modified for a single one, hence the
+/// `T: Clone` requirement.
+///
+/// Like `&mut Arc<T>`, this uniquely borrows the `Arc<T>` column.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use std::sync::Arc;
+/// let mut world = World::new();
+/// let mesh = Arc::new(vec![1, 2, 3]);
+/// let a = world.spawn((mesh.clone(), true));
+/// let b = world.spawn((mesh.clone(),));
+/// for (_, mut mesh) in world.query_mut::<With<bool, CowMut<Vec<i32>>>>() {
+///     mesh.push(4);
+/// }
+/// assert_eq!(**world.get::<Arc<Vec<i32>>>(a).unwrap(), [1, 2, 3, 4]);
+/// assert!(Arc::ptr_eq(&*world.get::<Arc<Vec<i32>>>(b).unwrap(), &mesh));
+/// ```
+pub struct CowMut<T>(PhantomData<fn(T)>);
+
+impl<T: Clone + Component> Query for CowMut<T> {
+    type Fetch = FetchCowMut<T>;
+}
+
+#[doc(hidden)]
+pub struct FetchCowMut<T>(NonNull<Arc<T>>);
+
+unsafe impl<'a, T: Clone + Component> Fetch<'a> for FetchCowMut<T> {
+    type Item = CowGuard<'a, T>;
+
+    type State = usize;
+
+    fn dangling() -> Self {
+        Self(NonNull::dangling())
+    }
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        if archetype.has::<Arc<T>>() {
+            Some(Access::Write)
+        } else {
+            None
+        }
+    }
+
+    fn borrow(archetype: &Archetype, state: Self::State) {
+        archetype.borrow_mut::<Arc<T>>(state);
+    }
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        archetype.get_state::<Arc<T>>()
+    }
+    fn execute(archetype: &'a Archetype, state: Self::State) -> Self {
+        Self(archetype.get_base::<Arc<T>>(state))
+    }
+    fn release(archetype: &Archetype, state: Self::State) {
+        archetype.release_mut::<Arc<T>>(state);
+    }
+
+    fn for_each_borrow(mut f: impl FnMut(TypeId, bool)) {
+        f(TypeId::of::<Arc<T>>(), true);
+    }
+
+    unsafe fn get(&self, n: usize) -> Self::Item {
+        CowGuard(&mut *self.0.as_ptr().add(n))
+    }
+}
+
+/// Copy-on-write access to an `Arc<T>` component, yielded by [`CowMut`]
+pub struct CowGuard<'a, T>(&'a mut Arc<T>);
+
+impl<'a, T> CowGuard<'a, T> {
+    /// Access the underlying `Arc` directly
+    pub fn arc(&self) -> &Arc<T> {
+        self.0
+    }
+}
+
+impl<T> core::ops::Deref for CowGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: Clone> core::ops::DerefMut for CowGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        Arc::make_mut(self.0)
+    }
+}
+
+/// Types that are `#[repr(transparent)]` wrappers around a single `Inner` value
+///
+/// Enables direct access to the wrapped value of components with the [`Unwrap`] query. Rather
+/// than implementing this manually, prefer `#[derive(Transparent)]`, which checks the
+/// requirements below.
+///
+/// # Safety
+///
+/// `Self` must be `#[repr(transparent)]`, and `Inner` must be the type of its only field.
+pub unsafe trait Transparent: Component {
+    /// The type of the wrapped value
+    type Inner;
+}
+
+/// Query that yields mutable access to the value wrapped by each `T` component
+///
+/// Like `&mut T`, but yields `&mut T::Inner`, which allows generic code to operate directly on the
+/// contents of newtype components.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// #[repr(transparent)]
+/// struct Health(f32);
+///
+/// // Prefer `#[derive(Transparent)]` when the `macros` feature is enabled
+/// unsafe impl Transparent for Health {
+///     type Inner = f32;
+/// }
+///
+/// fn decay(x: &mut f32) {
+///     *x *= 0.5;
+/// }
+///
+/// let mut world = World::new();
+/// let e = world.spawn((Health(10.0),));
+/// for (_, health) in world.query_mut::<Unwrap<Health>>() {
+///     decay(health);
+/// }
+/// assert_eq!(world.get::<Health>(e).unwrap().0, 5.0);
+/// ```
+pub struct Unwrap<T>(PhantomData<fn(T)>);
+
+impl<T: Transparent> Query for Unwrap<T> {
+    type Fetch = FetchUnwrap<T>;
+}
+
+#[doc(hidden)]
+pub struct FetchUnwrap<T>(FetchWrite<T>);
+
+unsafe impl<'a, T: Transparent> Fetch<'a> for FetchUnwrap<T> {
+    type Item = &'a mut T::Inner;
+
+    type State = usize;
+
+    fn dangling() -> Self {
+        Self(FetchWrite::dangling())
+    }
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        FetchWrite::<T>::access(archetype)
+    }
+
+    fn borrow(archetype: &Archetype, state: Self::State) {
+        FetchWrite::<T>::borrow(archetype, state);
+    }
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        FetchWrite::<T>::prepare(archetype)
+    }
+    fn execute(archetype: &'a Archetype, state: Self::State) -> Self {
+        Self(FetchWrite::execute(archetype, state))
+    }
+    fn release(archetype: &Archetype, state: Self::State) {
+        FetchWrite::<T>::release(archetype, state);
+    }
+
+    fn for_each_borrow(f: impl FnMut(TypeId, bool)) {
+        FetchWrite::<T>::for_each_borrow(f);
+    }
+
+    unsafe fn get(&self, n: usize) -> Self::Item {
+        // Sound because `Transparent` guarantees `T` has the same layout as `T::Inner`
+        &mut *(self.0 .0.as_ptr().add(n) as *mut T::Inner)
+    }
+}
+
 /// A borrow of a [`World`](crate::World) sufficient to execute the query `Q`
 ///
 /// Note that borrows are not released until this object is dropped.
+///
+/// Borrows are acquired once, on first use, and then reused by every subsequent call to
+/// [`iter`](Self::iter), [`view`](Self::view), and friends. Several `QueryBorrow`s over compatible
+/// components may be held at once, so interleaving or repeatedly re-entering multiple queries
+/// incurs no extra borrow-tracking traffic as long as they're kept alive for the duration.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// world.spawn((1, true));
+/// world.spawn((2, false));
+/// let mut numbers = world.query::<&mut i32>();
+/// let mut flags = world.query::<&bool>();
+/// for (e, &flag) in flags.iter() {
+///     if flag {
+///         *numbers.view().get_mut(e).unwrap() *= 10;
+///     }
+/// }
+/// let sum = numbers.iter().map(|(_, &mut x)| x).sum::<i32>();
+/// assert_eq!(sum, 12);
+/// ```
 pub struct QueryBorrow<'w, Q: Query> {
     meta: &'w [EntityMeta],
     archetypes: &'w [Archetype],
@@ -594,6 +953,37 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
         unsafe { QueryIter::new(self.meta, self.archetypes.iter()) }
     }
 
+    /// Call `f` for each result along with a shared context
+    ///
+    /// Equivalent to `iter().for_each(..)` with a closure capturing `ctx`, but lets systems written
+    /// as plain functions taking their frame-constant inputs be passed directly.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Frame {
+    ///     dt: f32,
+    /// }
+    ///
+    /// fn integrate(frame: &Frame, _: Entity, (pos, vel): (&mut f32, &i32)) {
+    ///     *pos += *vel as f32 * frame.dt;
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((0.0f32, 2));
+    /// world
+    ///     .query::<(&mut f32, &i32)>()
+    ///     .for_each_with(&Frame { dt: 0.5 }, integrate);
+    /// assert_eq!(*world.get::<f32>(a).unwrap(), 1.0);
+    /// ```
+    pub fn for_each_with<'q, C: ?Sized>(
+        &'q mut self,
+        ctx: &C,
+        mut f: impl FnMut(&C, Entity, QueryItem<'q, Q>),
+    ) {
+        self.iter().for_each(|(entity, item)| f(ctx, entity, item));
+    }
+
     /// Provide random access to the query results
     pub fn view(&mut self) -> View<'_, Q> {
         self.borrow();
@@ -606,7 +996,374 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
     // The lifetime narrowing here is required for soundness.
     pub fn iter_batched(&mut self, batch_size: u32) -> BatchedIter<'_, Q> {
         self.borrow();
-        unsafe { BatchedIter::new(self.meta, self.archetypes.iter(), batch_size) }
+        unsafe { BatchedIter::new(self.meta, self.archetypes.iter(), batch_size, false) }
+    }
+
+    /// Like `iter_batched`, but batch boundaries fall on cache line boundaries of the first
+    /// component borrowed by `Q`
+    ///
+    /// Each archetype's first batch is extended so that every subsequent batch begins on a
+    /// 64-byte line in the column of the first component `Q` borrows, and `batch_size` is rounded
+    /// up to a whole number of lines. This keeps batches processed on different threads from
+    /// sharing lines of that column and allows aligned vector loads. If the component's size
+    /// doesn't evenly divide a cache line, or `Q` borrows nothing, batches are laid out exactly as
+    /// by `iter_batched`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn_batch((0..100).map(|i| (i as u32,))).for_each(|_| {});
+    /// let mut query = world.query::<&u32>();
+    /// for batch in query.iter_batched_aligned(10) {
+    ///     let mut batch = batch.peekable();
+    ///     let &(_, first) = batch.peek().unwrap();
+    ///     let len = batch.count();
+    ///     if *first != 0 {
+    ///         assert_eq!(first as *const u32 as usize % 64, 0);
+    ///         assert!(len <= 16);
+    ///     }
+    /// }
+    /// ```
+    // The lifetime narrowing here is required for soundness.
+    pub fn iter_batched_aligned(&mut self, batch_size: u32) -> BatchedIter<'_, Q> {
+        self.borrow();
+        unsafe { BatchedIter::new(self.meta, self.archetypes.iter(), batch_size, true) }
+    }
+
+    /// Iterate over the entities whose `T` component satisfies `pred`
+    ///
+    /// Unlike [`Iterator::filter`], `pred` is evaluated against the raw component before the query
+    /// item is constructed, so non-matching entities are skipped cheaply. `pred` always receives
+    /// an immutable reference, even when `Q` accesses `T` mutably. Entities that lack a `T` are
+    /// skipped.
+    ///
+    /// Panics if `Q` doesn't borrow `T`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Health(f32);
+    /// let mut world = World::new();
+    /// let a = world.spawn((Health(-1.0),));
+    /// let b = world.spawn((Health(5.0), true));
+    /// let c = world.spawn((Health(-3.0), true));
+    /// let mut dead = world
+    ///     .query::<&mut Health>()
+    ///     .filter_fn(|h: &Health| h.0 < 0.0)
+    ///     .map(|(e, h)| {
+    ///         h.0 = 0.0;
+    ///         e
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// dead.sort();
+    /// assert_eq!(dead, [a, c]);
+    /// assert_eq!(world.get::<Health>(c).unwrap().0, 0.0);
+    /// ```
+    pub fn filter_fn<T, F>(&mut self, pred: F) -> FilterFnIter<'_, Q, T, F>
+    where
+        T: Component,
+        F: FnMut(&T) -> bool,
+    {
+        let mut borrowed = false;
+        Q::Fetch::for_each_borrow(|ty, _| borrowed |= ty == TypeId::of::<T>());
+        assert!(
+            borrowed,
+            "filter_fn predicate must read a component borrowed by the query"
+        );
+        self.borrow();
+        FilterFnIter {
+            meta: self.meta,
+            archetypes: self.archetypes.iter(),
+            iter: ChunkIter::empty(),
+            column: NonNull::dangling(),
+            pred,
+        }
+    }
+
+    /// Like iterating with `for_each`, but measures the time spent visiting each archetype
+    ///
+    /// `f` is invoked for every entity as usual. After each matching, non-empty archetype is
+    /// visited, `report` receives that archetype and the time spent running `f` on its entities.
+    /// Useful for finding which component combinations dominate the cost of a system. Ordinary
+    /// iteration is unaffected.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((1, true));
+    /// world.spawn((2, "abc"));
+    /// let mut sum = 0;
+    /// let mut reports = 0;
+    /// world.query::<&i32>().profiled(
+    ///     |_, &x| sum += x,
+    ///     |archetype, _elapsed| {
+    ///         assert!(archetype.has::<i32>());
+    ///         reports += 1;
+    ///     },
+    /// );
+    /// assert_eq!(sum, 3);
+    /// assert_eq!(reports, 2);
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn profiled<'q>(
+        &'q mut self,
+        mut f: impl FnMut(Entity, QueryItem<'q, Q>),
+        mut report: impl FnMut(&Archetype, std::time::Duration),
+    ) {
+        for (archetype, batch) in self.archetype_batches() {
+            if archetype.is_empty() {
+                continue;
+            }
+            let start = std::time::Instant::now();
+            for (entity, item) in batch {
+                f(entity, item);
+            }
+            report(archetype, start.elapsed());
+        }
+    }
+
+    /// Like [`profiled`](Self::profiled) without the reporting, but spread across `threads` threads
+    /// that are joined before returning
+    ///
+    /// Matching archetypes are handed out to the threads round-robin, so this only helps when the
+    /// query matches several archetypes of comparable size. Components are borrowed once for the
+    /// whole query. If `f` panics on any worker thread, the panic is propagated after all threads
+    /// have finished.
+    ///
+    /// Empty archetypes are skipped, and the `n`th non-empty matching archetype, in the order the
+    /// archetypes were created, goes to worker `n % threads`. Fresh threads are spawned on each
+    /// call, so no thread or core affinity is kept between calls.
+    ///
+    /// Provides basic parallelism without an external thread pool; see `iter_batched` for building
+    /// on one instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::sync::atomic::{AtomicI32, Ordering};
+    /// let mut world = World::new();
+    /// world.spawn((1, true));
+    /// world.spawn((2, "abc"));
+    /// world.spawn((3,));
+    /// let sum = AtomicI32::new(0);
+    /// world.query::<&mut i32>().scoped_for_each(2, |_, x| {
+    ///     *x *= 2;
+    ///     sum.fetch_add(*x, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(sum.into_inner(), 12);
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn scoped_for_each<'q>(
+        &'q mut self,
+        threads: usize,
+        f: impl Fn(Entity, QueryItem<'q, Q>) + Sync,
+    ) {
+        assert!(threads > 0, "scoped_for_each requires at least one thread");
+        let mut buckets = (0..threads).map(|_| Vec::new()).collect::<Vec<_>>();
+        let batches = self
+            .archetype_batches()
+            .filter(|(archetype, _)| !archetype.is_empty())
+            .map(|(_, batch)| batch);
+        for (i, batch) in batches.enumerate() {
+            buckets[i % threads].push(batch);
+        }
+        let f = &f;
+        let mut workers = Workers(Vec::with_capacity(threads));
+        for bucket in buckets {
+            let work: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                for (entity, item) in bucket.into_iter().flatten() {
+                    f(entity, item);
+                }
+            });
+            // Safety: `workers` joins every thread before this function returns or unwinds, so
+            // nothing borrowed by `work` is invalidated while the thread can still access it
+            let work = unsafe {
+                mem::transmute::<Box<dyn FnOnce() + Send + '_>, Box<dyn FnOnce() + Send>>(work)
+            };
+            workers.0.push(std::thread::spawn(work));
+        }
+        let mut panic = None;
+        for worker in workers.0.drain(..) {
+            if let Err(payload) = worker.join() {
+                panic.get_or_insert(payload);
+            }
+        }
+        if let Some(payload) = panic {
+            std::panic::resume_unwind(payload);
+        }
+    }
+
+    /// Describe how the query relates to each archetype in the world
+    ///
+    /// Debugging aid for queries that unexpectedly yield nothing. Each [`QueryExplanation`] records
+    /// whether an archetype matched, which of the component types borrowed by the query it lacks,
+    /// and which [`With`] or [`Without`] filters it fails. Does not borrow any components.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use core::any::TypeId;
+    /// let mut world = World::new();
+    /// world.spawn((1, true));
+    /// world.spawn((2,));
+    /// let explanations = world.query::<(&i32, &bool)>().explain();
+    /// let lone_i32 = explanations
+    ///     .iter()
+    ///     .find(|x| x.archetype.has::<i32>() && !x.archetype.has::<bool>())
+    ///     .unwrap();
+    /// assert!(!lone_i32.matched);
+    /// assert_eq!(lone_i32.missing, [TypeId::of::<bool>()]);
+    ///
+    /// let explanations = world.query::<Without<bool, &i32>>().explain();
+    /// let with_bool = explanations.iter().find(|x| x.archetype.has::<bool>()).unwrap();
+    /// assert!(!with_bool.matched);
+    /// assert_eq!(with_bool.excluded, [TypeId::of::<bool>()]);
+    /// ```
+    pub fn explain(&self) -> Vec<QueryExplanation<'w>> {
+        self.archetypes
+            .iter()
+            .map(|archetype| {
+                let mut missing = Vec::new();
+                Q::Fetch::for_each_borrow(|id, _| {
+                    if !archetype.has_dynamic(id) {
+                        missing.push(id);
+                    }
+                });
+                let mut required = Vec::new();
+                let mut excluded = Vec::new();
+                Q::Fetch::for_each_filter(|id, wanted| match (wanted, archetype.has_dynamic(id)) {
+                    (true, false) => required.push(id),
+                    (false, true) => excluded.push(id),
+                    _ => {}
+                });
+                QueryExplanation {
+                    archetype,
+                    matched: Q::Fetch::access(archetype).is_some(),
+                    missing,
+                    required,
+                    excluded,
+                }
+            })
+            .collect()
+    }
+
+    /// Visit each non-empty matching archetype along with a scratch slice of the same length
+    ///
+    /// For every archetype, `f` is called once with a slice holding one `init()` value per entity
+    /// and a [`Batch`] over all of that archetype's entities, in the same order. The scratch
+    /// storage is reused between archetypes. Useful for column-oriented algorithms such as prefix
+    /// sums that operate on whole archetypes at a time.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((1,));
+    /// world.spawn((2,));
+    /// world.spawn((3, true));
+    /// let mut sums = Vec::new();
+    /// world.query::<&i32>().for_each_archetype(
+    ///     || 0,
+    ///     |scratch, batch| {
+    ///         let mut sum = 0;
+    ///         for (slot, (_, &x)) in scratch.iter_mut().zip(batch) {
+    ///             sum += x;
+    ///             *slot = sum;
+    ///         }
+    ///         sums.push(scratch.to_vec());
+    ///     },
+    /// );
+    /// sums.sort();
+    /// assert_eq!(sums, [vec![1, 3], vec![3]]);
+    /// ```
+    pub fn for_each_archetype<'q, X>(
+        &'q mut self,
+        mut init: impl FnMut() -> X,
+        mut f: impl FnMut(&mut [X], Batch<'q, Q>),
+    ) {
+        let mut scratch = Vec::new();
+        for (archetype, batch) in self.archetype_batches() {
+            if archetype.is_empty() {
+                continue;
+            }
+            scratch.clear();
+            scratch.extend((0..archetype.len()).map(|_| init()));
+            f(&mut scratch, batch);
+        }
+    }
+
+    /// Borrow the query's components, then yield a batch covering each matching archetype,
+    /// including empty ones
+    fn archetype_batches<'q>(
+        &'q mut self,
+    ) -> impl Iterator<Item = (&'q Archetype, Batch<'q, Q>)> + 'q {
+        self.borrow();
+        let meta = self.meta;
+        self.archetypes.iter().filter_map(move |archetype| {
+            let state = Q::Fetch::prepare(archetype)?;
+            let batch = Batch {
+                meta,
+                state: ChunkIter {
+                    entities: archetype.entities(),
+                    fetch: Q::Fetch::execute(archetype, state),
+                    position: 0,
+                    len: archetype.len() as usize,
+                },
+            };
+            Some((archetype, batch))
+        })
+    }
+
+    /// Collect the query's results into groups of equal `key`, in ascending key order
+    ///
+    /// Entities are not stored in key order, so every result is buffered and sorted before
+    /// grouping. Order within each group follows iteration order. Useful for e.g. issuing one draw
+    /// call per material.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((1, 'a'));
+    /// world.spawn((2, 'b'));
+    /// world.spawn((1, 'c', true));
+    /// let groups = world
+    ///     .query::<(&i32, &char)>()
+    ///     .group_by(|&(&key, _)| key)
+    ///     .into_iter()
+    ///     .map(|(key, group)| (key, group.into_iter().map(|(_, (_, &c))| c).collect::<Vec<_>>()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(groups.len(), 2);
+    /// assert_eq!(groups[0].0, 1);
+    /// assert_eq!(groups[0].1.len(), 2);
+    /// assert_eq!(groups[1], (2, vec!['b']));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn group_by<'q, K: Ord>(
+        &'q mut self,
+        mut key: impl FnMut(&QueryItem<'q, Q>) -> K,
+    ) -> Vec<(K, Vec<(Entity, QueryItem<'q, Q>)>)> {
+        let mut items = self
+            .iter()
+            .map(|(entity, item)| (key(&item), entity, item))
+            .collect::<Vec<_>>();
+        items.sort_by(|x, y| x.0.cmp(&y.0));
+        let mut groups = Vec::<(K, Vec<_>)>::new();
+        for (key, entity, item) in items {
+            match groups.last_mut() {
+                Some((last, group)) if *last == key => group.push((entity, item)),
+                _ => groups.push((key, vec![(entity, item)])),
+            }
+        }
+        groups
     }
 
     fn borrow(&mut self) {
@@ -687,6 +1444,41 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
 unsafe impl<'w, Q: Query> Send for QueryBorrow<'w, Q> {}
 unsafe impl<'w, Q: Query> Sync for QueryBorrow<'w, Q> {}
 
+/// How a query relates to a single archetype, as reported by [`QueryBorrow::explain`]
+#[derive(Clone)]
+pub struct QueryExplanation<'w> {
+    /// The archetype in question
+    pub archetype: &'w Archetype,
+    /// Whether the query visits the archetype's entities
+    pub matched: bool,
+    /// Component types borrowed by the query that the archetype lacks
+    ///
+    /// May be non-empty for a matching archetype when those types are optional, as in
+    /// `Option<&T>`. Filters like [`With`] and [`Without`] borrow nothing and are listed in
+    /// `required` and `excluded` instead.
+    pub missing: Vec<TypeId>,
+    /// Component types required by a [`With`] filter that the archetype lacks
+    pub required: Vec<TypeId>,
+    /// Component types ruled out by a [`Without`] filter that the archetype has
+    pub excluded: Vec<TypeId>,
+}
+
+/// Worker threads spawned by [`QueryBorrow::scoped_for_each`], joined when dropped
+///
+/// Ensures the workers can't outlive the borrows lent to them, even if spawning a later worker
+/// panics.
+#[cfg(feature = "std")]
+struct Workers(Vec<std::thread::JoinHandle<()>>);
+
+#[cfg(feature = "std")]
+impl Drop for Workers {
+    fn drop(&mut self) {
+        for worker in self.0.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
 impl<'w, Q: Query> Drop for QueryBorrow<'w, Q> {
     fn drop(&mut self) {
         if self.borrowed {
@@ -768,6 +1560,38 @@ impl<'q, Q: Query> Iterator for QueryIter<'q, Q> {
         let n = self.len();
         (n, Some(n))
     }
+
+    // Specialized to traverse each archetype in a simple counted loop, which optimizes far better
+    // than repeated calls to `next`. `for_each` and many other adapters are implemented in terms of
+    // `fold`.
+    fn fold<B, F>(mut self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        let meta = self.meta;
+        let entity = |id: u32| Entity {
+            id,
+            generation: unsafe { meta.get_unchecked(id as usize).generation },
+        };
+        let mut acc = init;
+        // Finish any partially consumed archetype
+        while let Some((id, components)) = unsafe { self.iter.next() } {
+            acc = f(acc, (entity(id), components));
+        }
+        for archetype in self.archetypes {
+            let fetch = match Q::Fetch::prepare(archetype) {
+                Some(state) => Q::Fetch::execute(archetype, state),
+                None => continue,
+            };
+            let entities = archetype.entities().as_ptr();
+            for i in 0..archetype.len() as usize {
+                unsafe {
+                    acc = f(acc, (entity(*entities.add(i)), fetch.get(i)));
+                }
+            }
+        }
+        acc
+    }
 }
 
 impl<'q, Q: Query> ExactSizeIterator for QueryIter<'q, Q> {
@@ -781,6 +1605,71 @@ impl<'q, Q: Query> ExactSizeIterator for QueryIter<'q, Q> {
     }
 }
 
+/// Iterator over the entities of a query matching a predicate, returned by
+/// [`QueryBorrow::filter_fn`]
+pub struct FilterFnIter<'q, Q: Query, T, F> {
+    meta: &'q [EntityMeta],
+    archetypes: SliceIter<'q, Archetype>,
+    iter: ChunkIter<Q>,
+    /// Base of the `T` column of the archetype `iter` is traversing
+    column: NonNull<T>,
+    pred: F,
+}
+
+unsafe impl<'q, Q: Query, T: Component, F: Send> Send for FilterFnIter<'q, Q, T, F> {}
+unsafe impl<'q, Q: Query, T: Component, F: Sync> Sync for FilterFnIter<'q, Q, T, F> {}
+
+impl<'q, Q, T, F> Iterator for FilterFnIter<'q, Q, T, F>
+where
+    Q: Query,
+    T: Component,
+    F: FnMut(&T) -> bool,
+{
+    type Item = (Entity, QueryItem<'q, Q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.iter.position == self.iter.len {
+                let archetype = self.archetypes.next()?;
+                let (state, column) =
+                    match (Q::Fetch::prepare(archetype), archetype.get_state::<T>()) {
+                        (Some(state), Some(column)) => (state, column),
+                        _ => {
+                            self.iter = ChunkIter::empty();
+                            continue;
+                        }
+                    };
+                self.column = archetype.get_base(column);
+                self.iter = ChunkIter {
+                    entities: archetype.entities(),
+                    fetch: Q::Fetch::execute(archetype, state),
+                    position: 0,
+                    len: archetype.len() as usize,
+                };
+                continue;
+            }
+            // The shared reference passed to `pred` ends before the item is constructed, so this
+            // can't alias even if `Q` accesses `T` uniquely.
+            if !(self.pred)(unsafe { &*self.column.as_ptr().add(self.iter.position) }) {
+                self.iter.position += 1;
+                continue;
+            }
+            let (id, components) = unsafe { self.iter.next()? };
+            return Some((
+                Entity {
+                    id,
+                    generation: unsafe { self.meta.get_unchecked(id as usize).generation },
+                },
+                components,
+            ));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
 /// A query builder that's convertible directly into an iterator
 pub struct QueryMut<'q, Q: Query> {
     iter: QueryIter<'q, Q>,
@@ -890,6 +1779,8 @@ pub struct BatchedIter<'q, Q: Query> {
     archetypes: SliceIter<'q, Archetype>,
     batch_size: u32,
     batch: u32,
+    /// Whether batches should begin on cache line boundaries
+    aligned: bool,
 }
 
 impl<'q, Q: Query> BatchedIter<'q, Q> {
@@ -901,6 +1792,7 @@ impl<'q, Q: Query> BatchedIter<'q, Q> {
         meta: &'q [EntityMeta],
         archetypes: SliceIter<'q, Archetype>,
         batch_size: u32,
+        aligned: bool,
     ) -> Self {
         Self {
             _marker: PhantomData,
@@ -908,7 +1800,41 @@ impl<'q, Q: Query> BatchedIter<'q, Q> {
             archetypes,
             batch_size,
             batch: 0,
+            aligned,
+        }
+    }
+
+    /// Compute the number of extra elements in the first batch of `archetype` and the size of each
+    /// batch
+    ///
+    /// Computed in `u64` so that huge batch sizes can't overflow.
+    fn layout(&self, archetype: &Archetype) -> (u64, u64) {
+        const LINE: usize = 64;
+        let unaligned = (0, u64::from(self.batch_size));
+        if !self.aligned {
+            return unaligned;
+        }
+        let mut first = None;
+        Q::Fetch::for_each_borrow(|ty, _| {
+            if first.is_none() {
+                first = Some(ty);
+            }
+        });
+        let (base, size) = match first.and_then(|ty| archetype.column_base(ty)) {
+            Some(x) => x,
+            None => return unaligned,
+        };
+        if size == 0 || LINE % size != 0 {
+            return unaligned;
+        }
+        let per_line = (LINE / size) as u64;
+        let misalignment = (LINE - base.as_ptr() as usize % LINE) % LINE;
+        if misalignment % size != 0 {
+            return unaligned;
         }
+        let head = (misalignment / size) as u64;
+        let step = ((u64::from(self.batch_size.max(1)) + per_line - 1) / per_line) * per_line;
+        (head, step)
     }
 }
 
@@ -922,12 +1848,18 @@ impl<'q, Q: Query> Iterator for BatchedIter<'q, Q> {
         loop {
             let mut archetypes = self.archetypes.clone();
             let archetype = archetypes.next()?;
-            let offset = self.batch_size * self.batch;
-            if offset >= archetype.len() {
+            let (head, step) = self.layout(archetype);
+            let len = u64::from(archetype.len());
+            let offset = match self.batch {
+                0 => 0,
+                n => head + step * u64::from(n),
+            };
+            if offset >= len {
                 self.archetypes = archetypes;
                 self.batch = 0;
                 continue;
             }
+            let end = (head + step * (u64::from(self.batch) + 1)).min(len);
             let state = Q::Fetch::prepare(archetype);
             let fetch = state.map(|state| Q::Fetch::execute(archetype, state));
             if let Some(fetch) = fetch {
@@ -937,7 +1869,7 @@ impl<'q, Q: Query> Iterator for BatchedIter<'q, Q> {
                     state: ChunkIter {
                         entities: archetype.entities(),
                         fetch,
-                        len: (offset + self.batch_size.min(archetype.len() - offset)) as usize,
+                        len: end as usize,
                         position: offset as usize,
                     },
                 });
@@ -959,6 +1891,41 @@ pub struct Batch<'q, Q: Query> {
     state: ChunkIter<Q>,
 }
 
+impl<'q, Q: Query> Batch<'q, Q> {
+    /// Raw IDs of the entities this batch has yet to yield, in order
+    ///
+    /// Taken before iterating, an entity's index in this slice matches its position in the batch,
+    /// which is useful for chunk-local algorithms like finding neighbors within an archetype. Like
+    /// [`Archetype::ids`], convertible into [`Entity`]s with
+    /// [`World::find_entity_from_id`](crate::World::find_entity_from_id).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2,));
+    /// world.query::<&i32>().for_each_archetype(
+    ///     || (),
+    ///     |_, batch| {
+    ///         let ids = batch.ids();
+    ///         assert_eq!(ids, [a.id(), b.id()]);
+    ///         for (i, (entity, _)) in batch.enumerate() {
+    ///             assert_eq!(ids[i], entity.id());
+    ///         }
+    ///     },
+    /// );
+    /// ```
+    pub fn ids(&self) -> &'q [u32] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self.state.entities.as_ptr().add(self.state.position),
+                self.state.remaining(),
+            )
+        }
+    }
+}
+
 impl<'q, Q: Query> Iterator for Batch<'q, Q> {
     type Item = (Entity, QueryItem<'q, Q>);
 
@@ -977,6 +1944,106 @@ impl<'q, Q: Query> Iterator for Batch<'q, Q> {
 unsafe impl<'q, Q: Query> Send for Batch<'q, Q> {}
 unsafe impl<'q, Q: Query> Sync for Batch<'q, Q> {}
 
+/// A borrow of the components of a single type chosen at runtime, returned by
+/// [`World::query_dynamic`]
+///
+/// Borrows are released when this object is dropped.
+pub struct DynamicQueryBorrow<'w> {
+    meta: &'w [EntityMeta],
+    archetypes: &'w [Archetype],
+    ty: TypeId,
+}
+
+impl<'w> DynamicQueryBorrow<'w> {
+    pub(crate) fn new(meta: &'w [EntityMeta], archetypes: &'w [Archetype], ty: TypeId) -> Self {
+        for x in archetypes {
+            if let Some(state) = x.get_dynamic_state(ty) {
+                x.borrow_raw(state);
+            }
+        }
+        Self {
+            meta,
+            archetypes,
+            ty,
+        }
+    }
+
+    /// Iterate over every entity with the component and the raw bytes of that component
+    ///
+    /// Bytes are exposed as `MaybeUninit` because components may contain padding.
+    // The lifetime narrowing here is required for soundness.
+    pub fn iter(&mut self) -> DynamicQueryIter<'_> {
+        DynamicQueryIter {
+            meta: self.meta,
+            archetypes: self.archetypes.iter(),
+            ty: self.ty,
+            column: &[],
+            entities: &[],
+            size: 0,
+        }
+    }
+}
+
+impl Drop for DynamicQueryBorrow<'_> {
+    fn drop(&mut self) {
+        for x in self.archetypes {
+            if let Some(state) = x.get_dynamic_state(self.ty) {
+                x.release_raw(state);
+            }
+        }
+    }
+}
+
+unsafe impl Send for DynamicQueryBorrow<'_> {}
+unsafe impl Sync for DynamicQueryBorrow<'_> {}
+
+/// Iterator over the components of a type chosen at runtime, returned by
+/// [`DynamicQueryBorrow::iter`]
+pub struct DynamicQueryIter<'q> {
+    meta: &'q [EntityMeta],
+    archetypes: SliceIter<'q, Archetype>,
+    ty: TypeId,
+    /// Remaining components of the current archetype
+    column: &'q [MaybeUninit<u8>],
+    /// Remaining entity IDs of the current archetype
+    entities: &'q [u32],
+    size: usize,
+}
+
+impl<'q> Iterator for DynamicQueryIter<'q> {
+    type Item = (Entity, &'q [MaybeUninit<u8>]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((&id, rest)) = self.entities.split_first() {
+                self.entities = rest;
+                let (component, column) = self.column.split_at(self.size);
+                self.column = column;
+                let entity = Entity {
+                    id,
+                    generation: self.meta[id as usize].generation,
+                };
+                return Some((entity, component));
+            }
+            let archetype = self.archetypes.next()?;
+            let (base, size) = match archetype.column_base(self.ty) {
+                Some(x) => x,
+                None => continue,
+            };
+            let len = archetype.len() as usize;
+            // Safe because the column is borrowed by the `DynamicQueryBorrow` this came from
+            unsafe {
+                self.column = core::slice::from_raw_parts(base.as_ptr().cast(), size * len);
+                self.entities = core::slice::from_raw_parts(archetype.entities().as_ptr(), len);
+            }
+            self.size = size;
+        }
+    }
+}
+
+unsafe impl Send for DynamicQueryIter<'_> {}
+unsafe impl Sync for DynamicQueryIter<'_> {}
+
 macro_rules! tuple_impl {
     ($($name: ident),*) => {
         unsafe impl<'a, $($name: Fetch<'a>),*> Fetch<'a> for ($($name,)*) {
@@ -1023,6 +2090,11 @@ macro_rules! tuple_impl {
                 $($name::for_each_borrow(&mut f);)*
             }
 
+            #[allow(unused_variables, unused_mut)]
+            fn for_each_filter(mut f: impl FnMut(TypeId, bool)) {
+                $($name::for_each_filter(&mut f);)*
+            }
+
             #[allow(unused_variables, clippy::unused_unit)]
             unsafe fn get(&self, n: usize) -> Self::Item {
                 #[allow(non_snake_case)]
@@ -1043,12 +2115,26 @@ macro_rules! tuple_impl {
 smaller_tuples_too!(tuple_impl, O, N, M, L, K, J, I, H, G, F, E, D, C, B, A);
 
 /// A prepared query can be stored independently of the [`World`] to amortize query set-up costs.
+///
+/// Caches the set of archetypes matching `Q` along with the per-archetype state needed to fetch
+/// from them, so that repeated uses skip archetype matching entirely. The cache is rebuilt when the
+/// prepared query is used with a different world, or when the world has gained archetypes since it
+/// was last used, which is cheap to check. Column addresses are still looked up on every use, since
+/// they change whenever an archetype grows.
+///
+/// A `PreparedQuery` borrows nothing from the world between uses, so it can be stored in a
+/// long-lived system struct and sent between threads. Using it requires `&mut self`, so each one
+/// serves one query at a time; use one per thread to query a shared world concurrently.
 pub struct PreparedQuery<Q: Query> {
     memo: (u64, u32),
     state: Box<[(usize, <Q::Fetch as Fetch<'static>>::State)]>,
     fetch: Box<[Option<Q::Fetch>]>,
 }
 
+// The cached fetches hold pointers into the world, but are overwritten before every use
+unsafe impl<Q: Query> Send for PreparedQuery<Q> {}
+unsafe impl<Q: Query> Sync for PreparedQuery<Q> {}
+
 impl<Q: Query> Default for PreparedQuery<Q> {
     fn default() -> Self {
         Self::new()
@@ -1485,4 +2571,17 @@ mod tests {
         assert!(Access::Read > Access::Iterate);
         assert!(Some(Access::Iterate) > None);
     }
+
+    #[test]
+    fn query_access() {
+        let access = QueryAccess::of::<(&i32, &mut bool, Or<&i32, &mut u8>, Without<f32, &u16>)>();
+        assert_eq!(access.reads().len(), 2);
+        assert!(access.reads().contains(&TypeId::of::<i32>()));
+        assert!(access.reads().contains(&TypeId::of::<u16>()));
+        assert_eq!(access.writes().len(), 2);
+        assert!(access.writes().contains(&TypeId::of::<bool>()));
+        assert!(access.writes().contains(&TypeId::of::<u8>()));
+        assert!(!access.conflicts(&QueryAccess::of::<(&i32, &f32)>()));
+        assert!(access.conflicts(&QueryAccess::of::<&mut u16>()));
+    }
 }
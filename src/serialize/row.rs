@@ -17,7 +17,7 @@ use serde::{
     Deserializer, Serialize, Serializer,
 };
 
-use crate::{Component, EntityBuilder, EntityRef, World};
+use crate::{Component, EntityBuilder, EntityRef, Query, World};
 
 /// Implements serialization of individual entities
 ///
@@ -103,6 +103,35 @@ where
     seq.end()
 }
 
+/// Serialize the entities of a [`World`] that satisfy the query `Q`
+///
+/// Like [`serialize`], but skips entities that `Q` wouldn't match, e.g. to save only entities
+/// tagged with a `Persistent` marker component. No components are borrowed by `Q` itself; which
+/// components of each matching entity are written, whether all of them or only those named by `Q`,
+/// remains up to `context`.
+pub fn serialize_satisfying<Q, C, S>(
+    world: &World,
+    context: &mut C,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    Q: Query,
+    C: SerializeContext,
+    S: Serializer,
+{
+    let len = world
+        .archetypes()
+        .filter(|x| x.access::<Q>().is_some())
+        .map(|x| x.len() as usize)
+        .sum();
+    let mut seq = serializer.serialize_map(Some(len))?;
+    for entity in world.iter().filter(|x| x.satisfies::<Q>()) {
+        seq.serialize_key(&entity.entity())?;
+        seq.serialize_value(&SerializeComponents(RefCell::new((context, Some(entity)))))?;
+    }
+    seq.end()
+}
+
 struct SerializeComponents<'a, C>(RefCell<(&'a mut C, Option<EntityRef<'a>>)>);
 
 impl<'a, C: SerializeContext> Serialize for SerializeComponents<'a, C> {
@@ -403,4 +432,42 @@ mod tests {
             Token::MapEnd,
         ])
     }
+
+    #[test]
+    #[rustfmt::skip]
+    fn satisfying() {
+        use serde_test::{Token, assert_ser_tokens};
+
+        struct Moving<'a>(&'a World);
+
+        impl Serialize for Moving<'_> {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                serialize_satisfying::<&Velocity, _, _>(self.0, &mut Context, s)
+            }
+        }
+
+        let mut world = World::new();
+        world.spawn((Position([0.0, 0.0, 0.0]),));
+        let e1 = world.spawn((Velocity([1.0, 1.0, 1.0]),));
+        world.spawn((Position([2.0, 2.0, 2.0]),));
+
+        assert_ser_tokens(&Moving(&world), &[
+            Token::Map { len: Some(1) },
+
+            Token::U64(e1.to_bits().into()),
+            Token::Map { len: None },
+
+            Token::UnitVariant { name: "ComponentId", variant: "Velocity" },
+            Token::NewtypeStruct { name: "Velocity" },
+            Token::Tuple { len: 3 },
+            Token::F32(1.0),
+            Token::F32(1.0),
+            Token::F32(1.0),
+            Token::TupleEnd,
+
+            Token::MapEnd,
+
+            Token::MapEnd,
+        ])
+    }
 }
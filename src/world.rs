@@ -24,9 +24,9 @@ use crate::alloc::boxed::Box;
 use crate::archetype::{Archetype, TypeIdMap, TypeInfo};
 use crate::entities::{Entities, EntityMeta, Location, ReserveEntitiesIterator};
 use crate::{
-    Bundle, Column, ColumnBatch, ColumnMut, DynamicBundle, Entity, EntityRef, Fetch,
-    MissingComponent, NoSuchEntity, Query, QueryBorrow, QueryItem, QueryMut, QueryOne, Ref, RefMut,
-    TakenEntity,
+    Bundle, Column, ColumnBatch, ColumnMut, CommandBuffer, DynamicBundle, DynamicQueryBorrow,
+    Entity, EntityBuilder, EntityRef, Fetch, MissingComponent, NoSuchEntity, Query, QueryBorrow,
+    QueryItem, QueryMut, QueryOne, Ref, RefMut, TakenEntity, Without,
 };
 
 /// An unordered collection of entities, each having any number of distinctly typed components
@@ -58,6 +58,11 @@ pub struct World {
     /// Maps source archetype and static bundle types to the archetype that an entity is moved to
     /// after removing the components from that bundle.
     remove_edges: IndexTypeIdMap<u32>,
+    /// Entities killed with `soft_despawn` since the last `clear_trackers`
+    graveyard: Option<Box<World>>,
+    /// Entities moved between archetypes by `insert` or `remove`, and their source and destination
+    /// archetypes, if recording is enabled
+    archetype_events: Option<Vec<(Entity, u32, u32)>>,
     id: u64,
 }
 
@@ -79,6 +84,8 @@ impl World {
             bundle_to_archetype: HashMap::default(),
             insert_edges: HashMap::default(),
             remove_edges: HashMap::default(),
+            graveyard: None,
+            archetype_events: None,
             id,
         }
     }
@@ -95,6 +102,10 @@ impl World {
     ///
     /// Any type that satisfies `Send + Sync + 'static` can be used as a component.
     ///
+    /// IDs of despawned entities are reused last-in, first-out, so the most recently despawned ID
+    /// is handed out first. Entity allocation is fully deterministic: worlds subjected to the same
+    /// sequence of spawns, despawns, and reservations assign the same entities.
+    ///
     /// # Example
     /// ```
     /// # use hecs::*;
@@ -114,6 +125,33 @@ impl World {
         entity
     }
 
+    /// Create an entity with certain components and access it immediately
+    ///
+    /// Like [`spawn`](Self::spawn), but also returns an [`EntityRef`] to the new entity, so its
+    /// components can be adjusted without a second lookup. Handy when a component's final value
+    /// depends on the newly allocated [`Entity`].
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let (a, e) = world.spawn_mut((Entity::DANGLING, 42));
+    /// *e.get_mut::<Entity>().unwrap() = a;
+    /// assert_eq!(*world.get::<Entity>(a).unwrap(), a);
+    /// ```
+    pub fn spawn_mut(&mut self, components: impl DynamicBundle) -> (Entity, EntityRef<'_>) {
+        let entity = self.spawn(components);
+        let loc = self.entities.meta[entity.id as usize].location;
+        let entity_ref = unsafe {
+            EntityRef::new(
+                &self.archetypes.archetypes[loc.archetype as usize],
+                entity,
+                loc.index,
+            )
+        };
+        (entity, entity_ref)
+    }
+
     /// Create an entity with certain components and a specific [`Entity`] handle.
     ///
     /// See [`spawn`](Self::spawn).
@@ -213,6 +251,29 @@ impl World {
         }
     }
 
+    /// Like [`spawn_batch`](Self::spawn_batch), but appends the new entities to `out`
+    ///
+    /// Lets systems that repeatedly spawn bursts of entities reuse one buffer rather than
+    /// allocating a fresh collection each time.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let mut entities = Vec::new();
+    /// world.spawn_batch_into((0..10).map(|i| (i,)), &mut entities);
+    /// world.spawn_batch_into((10..20).map(|i| (i,)), &mut entities);
+    /// assert_eq!(entities.len(), 20);
+    /// assert_eq!(*world.get::<i32>(entities[15]).unwrap(), 15);
+    /// ```
+    pub fn spawn_batch_into<I>(&mut self, iter: I, out: &mut Vec<Entity>)
+    where
+        I: IntoIterator,
+        I::Item: Bundle + 'static,
+    {
+        out.extend(self.spawn_batch(iter));
+    }
+
     /// Super-efficiently spawn the contents of a [`ColumnBatch`]
     ///
     /// The fastest, but most specialized, way to spawn large numbers of entities. Useful for high
@@ -316,6 +377,93 @@ impl World {
         Ok(())
     }
 
+    /// Destroy an entity, keeping its components readable until the next
+    /// [`clear_trackers`](Self::clear_trackers)
+    ///
+    /// Entity removal happens in two phases:
+    /// 1. `soft_despawn` immediately removes `entity` from this world, exactly like
+    ///    [`despawn`](Self::despawn): its handle is no longer [`contains`](Self::contains)ed, `get`
+    ///    and friends report [`NoSuchEntity`], and its ID may be reused. Its components are moved
+    ///    into the [`graveyard`](Self::graveyard) world alongside a [`Dead`] component recording
+    ///    the original handle.
+    /// 2. [`clear_trackers`](Self::clear_trackers) destroys everything in the graveyard, as does
+    ///    [`clear`](Self::clear).
+    ///
+    /// This allows e.g. a cleanup system to read the final state of entities that died during a
+    /// frame.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, "abc"));
+    /// world.soft_despawn(a).unwrap();
+    /// assert!(!world.contains(a));
+    /// let graveyard = world.graveyard().unwrap();
+    /// let corpses = graveyard
+    ///     .query::<(&Dead, &i32)>()
+    ///     .iter()
+    ///     .map(|(_, (dead, &x))| (dead.0, x))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(corpses, [(a, 123)]);
+    /// world.clear_trackers();
+    /// assert!(world.graveyard().unwrap().is_empty());
+    /// ```
+    pub fn soft_despawn(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        let taken = self.take(entity)?;
+        let mut corpse = EntityBuilder::new();
+        corpse.add_bundle(taken).add(Dead(entity));
+        self.graveyard
+            .get_or_insert_with(Box::default)
+            .spawn(corpse.build());
+        Ok(())
+    }
+
+    /// Entities destroyed by [`soft_despawn`](Self::soft_despawn) since the last
+    /// [`clear_trackers`](Self::clear_trackers)
+    ///
+    /// Returns `None` if `soft_despawn` has never been called. Each entity carries a [`Dead`]
+    /// component holding its handle in this world; handles within the graveyard itself are
+    /// unrelated.
+    pub fn graveyard(&self) -> Option<&World> {
+        self.graveyard.as_deref()
+    }
+
+    /// Finalize per-frame bookkeeping
+    ///
+    /// Intended to be called once at the end of each frame. In order, this:
+    /// 1. Destroys all entities in the [`graveyard`](Self::graveyard), dropping their components
+    /// 2. Discards any archetype events not yet taken with
+    ///    [`drain_archetype_events`](Self::drain_archetype_events); recording remains enabled if it
+    ///    was
+    ///
+    /// Everything soft-despawned or recorded before the call is observable until it returns, and
+    /// nothing afterwards. Live entities are unaffected.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.record_archetype_events(true);
+    /// let a = world.spawn((123,));
+    /// world.insert_one(a, true).unwrap();
+    /// world.soft_despawn(a).unwrap();
+    /// world.clear_trackers();
+    /// assert!(world.graveyard().unwrap().is_empty());
+    /// assert_eq!(world.drain_archetype_events().count(), 0);
+    /// let b = world.spawn((456,));
+    /// world.insert_one(b, true).unwrap();
+    /// assert_eq!(world.drain_archetype_events().count(), 1);
+    /// ```
+    pub fn clear_trackers(&mut self) {
+        if let Some(ref mut graveyard) = self.graveyard {
+            graveyard.clear();
+        }
+        if let Some(ref mut events) = self.archetype_events {
+            events.clear();
+        }
+    }
+
     /// Ensure at least `additional` entities with exact components `T` can be spawned without reallocating
     pub fn reserve<T: Bundle + 'static>(&mut self, additional: u32) {
         self.reserve_inner::<T>(additional);
@@ -342,11 +490,16 @@ impl World {
     /// Despawn all entities
     ///
     /// Preserves allocated storage for reuse but clears metadata so that [`Entity`] values will repeat (in contrast to [`despawn`][Self::despawn]).
+    /// Also empties the [`graveyard`](Self::graveyard), since the handles recorded there would
+    /// otherwise refer to unrelated new entities.
     pub fn clear(&mut self) {
         for x in &mut self.archetypes.archetypes {
             x.clear();
         }
         self.entities.clear();
+        if let Some(ref mut graveyard) = self.graveyard {
+            graveyard.clear();
+        }
     }
 
     /// Whether `entity` still exists
@@ -381,6 +534,12 @@ impl World {
     /// its dynamic borrows from the world to be released. Similarly, lifetime rules ensure that
     /// references obtained from a query cannot outlive the [`QueryBorrow`].
     ///
+    /// Any number of threads may run queries that only read components on the same `&World`
+    /// concurrently. Spawning, despawning, and adding or removing components all require
+    /// `&mut World`, so such queries, including those run through a
+    /// [`PreparedQuery`](crate::PreparedQuery), always observe the same consistent set of
+    /// archetypes.
+    ///
     /// # Example
     /// ```
     /// # use hecs::*;
@@ -409,6 +568,293 @@ impl World {
         QueryMut::new(&self.entities.meta, &mut self.archetypes.archetypes)
     }
 
+    /// Like [`query_mut`](Self::query_mut), but first gives every entity matching `Q` a
+    /// `T::default()` if it lacks a `T`
+    ///
+    /// Because the world is uniquely borrowed, the missing components are inserted immediately,
+    /// before iteration begins, so every yielded item has a `&mut T`. Code holding only `&World`
+    /// can get a similar effect by recording insertions in a [`CommandBuffer`], at the cost of
+    /// the new components only becoming visible after it runs.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2, 10u64));
+    /// world.spawn((20u64,));
+    /// for (_, (&x, total)) in world.query_mut_or_default::<&i32, u64>() {
+    ///     *total += x as u64;
+    /// }
+    /// assert_eq!(*world.get::<u64>(a).unwrap(), 1);
+    /// assert_eq!(*world.get::<u64>(b).unwrap(), 12);
+    /// ```
+    pub fn query_mut_or_default<Q: Query, T: Component + Default>(
+        &mut self,
+    ) -> QueryMut<'_, (Q, &mut T)> {
+        let missing = self
+            .query_mut::<Without<T, Q>>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in missing {
+            self.insert_one(entity, T::default()).unwrap();
+        }
+        self.query_mut()
+    }
+
+    /// Visit every entity matching `Q` along with a [`CommandBuffer`] for structural changes
+    ///
+    /// Commands recorded by `f` are applied with [`CommandBuffer::run_on`] once iteration
+    /// completes, so they are never visible within the same iteration. As with `run_on`, insertions
+    /// and spawns are applied first, then removals, then despawns.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2, true));
+    /// world.for_each_deferred::<&i32>(|entity, &x, cmd| {
+    ///     if x > 1 {
+    ///         cmd.remove::<(bool,)>(entity);
+    ///     } else {
+    ///         cmd.insert(entity, ("small",));
+    ///     }
+    /// });
+    /// assert!(world.entity(a).unwrap().has::<&str>());
+    /// assert!(!world.entity(b).unwrap().has::<bool>());
+    /// ```
+    pub fn for_each_deferred<Q: Query>(
+        &mut self,
+        mut f: impl for<'a> FnMut(Entity, QueryItem<'a, Q>, &mut CommandBuffer),
+    ) {
+        let mut cmd = CommandBuffer::new();
+        for (entity, item) in self.query_mut::<Q>() {
+            f(entity, item, &mut cmd);
+        }
+        cmd.run_on(self);
+    }
+
+    /// Apply `f` to every component of type `T` in the world
+    ///
+    /// Walks each archetype's `T` column directly, without resolving entities, making this the
+    /// cheapest way to update all values of a type at once, e.g. after reloading the asset they
+    /// were derived from.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1, true));
+    /// let b = world.spawn((2,));
+    /// world.replace_all::<i32>(|x| *x *= 10);
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 10);
+    /// assert_eq!(*world.get::<i32>(b).unwrap(), 20);
+    /// ```
+    pub fn replace_all<T: Component>(&mut self, mut f: impl FnMut(&mut T)) {
+        for archetype in &mut self.archetypes.archetypes {
+            let state = match archetype.get_state::<T>() {
+                Some(state) => state,
+                None => continue,
+            };
+            let base = archetype.get_base::<T>(state);
+            // Safety: `&mut self` guarantees no outstanding borrows, and the first `len` elements
+            // of the column are initialized
+            let column =
+                unsafe { core::slice::from_raw_parts_mut(base.as_ptr(), archetype.len() as usize) };
+            column.iter_mut().for_each(&mut f);
+        }
+    }
+
+    /// Panic if any component is currently borrowed
+    ///
+    /// Borrows are released when the [`Ref`], [`RefMut`], or [`QueryBorrow`] holding them is
+    /// dropped, so one that is leaked or kept alive too long surfaces as a panic in some unrelated
+    /// later access. Calling this at system boundaries, e.g. in tests, catches the leak closer to
+    /// its source.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((42,));
+    /// let value = world.get::<i32>(a).unwrap();
+    /// drop(value);
+    /// world.assert_no_borrows();
+    /// ```
+    pub fn assert_no_borrows(&self) {
+        for archetype in self.archetypes_inner() {
+            archetype.assert_no_borrows();
+        }
+    }
+
+    /// Read the components of a type chosen at runtime
+    ///
+    /// Immediately borrows the components of type `ty` in every archetype that has them, until the
+    /// returned value is dropped. Each entity's component is exposed as the raw bytes of its
+    /// in-memory representation, which are only meaningful to code that knows the type's layout,
+    /// e.g. a scripting layer that registered its [`TypeInfo`]. Panics if the components are
+    /// already uniquely borrowed.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let a = world.spawn((42u32, true));
+    /// let b = world.spawn((7u32,));
+    /// world.spawn(("abc",));
+    /// let mut query = world.query_dynamic(TypeId::of::<u32>());
+    /// let mut values = query
+    ///     .iter()
+    ///     // Sound because `u32` has no padding
+    ///     .map(|(e, bytes)| (e, unsafe { bytes.as_ptr().cast::<u32>().read_unaligned() }))
+    ///     .collect::<Vec<_>>();
+    /// values.sort();
+    /// assert_eq!(values, [(a, 42), (b, 7)]);
+    /// ```
+    pub fn query_dynamic(&self, ty: TypeId) -> DynamicQueryBorrow<'_> {
+        DynamicQueryBorrow::new(&self.entities.meta, &self.archetypes.archetypes, ty)
+    }
+
+    /// Temporarily attach a `T` component to every entity matching `Q`
+    ///
+    /// Computes a `T` for each entity that matches `Q` and doesn't already have a `T` using `init`,
+    /// inserts them, runs `body`, and finally removes the `T`s again from every such entity that
+    /// still exists. The `T`s are removed even if `body` panics. Entities that already had a `T`
+    /// are not modified. Useful for multi-pass algorithms that need per-entity intermediate state.
+    ///
+    /// Matching entities are moved one archetype at a time, rather than individually.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Rank(usize);
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((3.0f32,));
+    /// let b = world.spawn((1.0f32,));
+    /// let c = world.spawn((2.0f32,));
+    ///
+    /// let mut order = world.query_mut::<&f32>().into_iter().collect::<Vec<_>>();
+    /// order.sort_by(|x, y| x.1.partial_cmp(y.1).unwrap());
+    /// let order = order.into_iter().map(|(e, _)| e).collect::<Vec<_>>();
+    ///
+    /// let ranked = world.with_scratch::<&f32, _, _>(
+    ///     |e, _| Rank(order.iter().position(|&x| x == e).unwrap()),
+    ///     |world| {
+    ///         let mut ranked = world
+    ///             .query::<&Rank>()
+    ///             .iter()
+    ///             .map(|(e, rank)| (rank.0, e))
+    ///             .collect::<Vec<_>>();
+    ///         ranked.sort();
+    ///         ranked
+    ///     },
+    /// );
+    /// assert_eq!(ranked, [(0, b), (1, c), (2, a)]);
+    /// assert!(!world.entity(a).unwrap().has::<Rank>());
+    /// ```
+    pub fn with_scratch<Q, T, R>(
+        &mut self,
+        mut init: impl for<'a> FnMut(Entity, QueryItem<'a, Q>) -> T,
+        body: impl FnOnce(&mut World) -> R,
+    ) -> R
+    where
+        Q: Query,
+        T: Component,
+    {
+        self.flush();
+        // Queries visit each archetype's entities contiguously and in storage order, so consecutive
+        // results from the same archetype make up that whole archetype
+        let scratch = self
+            .query_mut::<Without<T, Q>>()
+            .into_iter()
+            .map(|(e, item)| (e, init(e, item)))
+            .collect::<Vec<_>>();
+        let mut groups = Vec::<(u32, Vec<Entity>, Vec<T>)>::new();
+        for (entity, value) in scratch {
+            let archetype = self.entities.meta[entity.id as usize].location.archetype;
+            match groups.last_mut() {
+                Some(group) if group.0 == archetype => {
+                    group.1.push(entity);
+                    group.2.push(value);
+                }
+                _ => groups.push((archetype, vec![entity], vec![value])),
+            }
+        }
+
+        let mut guard = RemoveScratch::<T> {
+            entities: Vec::new(),
+            world: self,
+            _marker: PhantomData,
+        };
+        for (archetype, entities, values) in groups {
+            guard.world.insert_column(archetype, values);
+            guard.entities.extend(entities);
+        }
+        body(guard.world)
+    }
+
+    /// Add a `T` to every entity in archetype `src`, taking one value per entity, in storage order,
+    /// from `values`
+    ///
+    /// Moves the archetype's entities all at once. `src` must not have a `T` already.
+    fn insert_column<T: Component>(&mut self, src: u32, mut values: Vec<T>) {
+        let source = &self.archetypes.archetypes[src as usize];
+        debug_assert!(!source.has::<T>());
+        assert_eq!(values.len(), source.len() as usize);
+        let mut info = source.types().to_vec();
+        info.push(TypeInfo::of::<T>());
+        info.sort_unstable();
+        let elements = info.iter().map(|x| x.id()).collect::<Box<_>>();
+        let dst = self.archetypes.get(elements, move || info);
+
+        let (source, target) = index2(&mut self.archetypes.archetypes, src as usize, dst as usize);
+        let len = source.len();
+        target.reserve(len);
+        let base = target.len();
+        unsafe {
+            for ty in source.types() {
+                let (from, size) = source.column_base(ty.id()).unwrap();
+                let (to, _) = target.column_base(ty.id()).unwrap();
+                ptr::copy_nonoverlapping(
+                    from.as_ptr(),
+                    to.as_ptr().add(base as usize * size),
+                    len as usize * size,
+                );
+            }
+            let state = target.get_state::<T>().unwrap();
+            ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                target.get_base::<T>(state).as_ptr().add(base as usize),
+                len as usize,
+            );
+            // The values now belong to the archetype; only free the buffer
+            values.set_len(0);
+
+            for index in 0..len {
+                let id = source.entity_id(index);
+                target.set_entity_id((base + index) as usize, id);
+                let meta = &mut self.entities.meta[id as usize];
+                meta.location = Location {
+                    archetype: dst,
+                    index: base + index,
+                };
+                if let Some(ref mut events) = self.archetype_events {
+                    let entity = Entity {
+                        generation: meta.generation,
+                        id,
+                    };
+                    events.push((entity, src, dst));
+                }
+            }
+            target.set_len(base + len);
+            source.set_len(0);
+        }
+    }
+
     pub(crate) fn memo(&self) -> (u64, u32) {
         (self.id, self.archetypes.generation())
     }
@@ -473,6 +919,29 @@ impl World {
     ///
     /// Panics if the component is already uniquely borrowed from another entity with the same
     /// components.
+    ///
+    /// The returned guard may live as long as the borrow of the world, so it can be returned from
+    /// functions taking `&World`. A plain `&T` can't be handed out for that long, since nothing
+    /// would then prevent [`get_mut`](Self::get_mut) from aliasing it. When the world is uniquely
+    /// borrowed, [`query_one_mut`](Self::query_one_mut) provides references tied directly to the
+    /// world's lifetime without any dynamic borrow tracking.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// fn name(world: &World, e: Entity) -> Ref<'_, &'static str> {
+    ///     world.get::<&'static str>(e).unwrap()
+    /// }
+    ///
+    /// fn name_mut(world: &mut World, e: Entity) -> &&'static str {
+    ///     world.query_one_mut::<&&'static str>(e).unwrap()
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// let e = world.spawn(("abc",));
+    /// assert_eq!(*name(&world, e), "abc");
+    /// assert_eq!(*name_mut(&mut world, e), "abc");
+    /// ```
     pub fn get<T: Component>(&self, entity: Entity) -> Result<Ref<'_, T>, ComponentError> {
         Ok(self
             .entity(entity)?
@@ -561,6 +1030,37 @@ impl World {
         self.insert_inner(entity, components, loc.archetype, loc)
     }
 
+    /// Like [`insert`](Self::insert), but fails rather than panicking if `components` contains more
+    /// than one component of the same type
+    ///
+    /// Useful when inserting bundles assembled at runtime, which can't be checked for duplicates
+    /// at compile time. `components` is dropped on failure, and `entity` is left unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123, "abc"));
+    /// assert_eq!(world.try_insert(e, (456, 789)), Err(InsertError::DuplicateComponent));
+    /// assert_eq!(*world.get::<i32>(e).unwrap(), 123);
+    /// world.try_insert(e, (456, true)).unwrap();
+    /// assert_eq!(*world.get::<i32>(e).unwrap(), 456);
+    /// ```
+    pub fn try_insert(
+        &mut self,
+        entity: Entity,
+        components: impl DynamicBundle,
+    ) -> Result<(), InsertError> {
+        self.flush();
+
+        let loc = self.entities.get(entity)?;
+        if has_duplicates(&components) {
+            return Err(InsertError::DuplicateComponent);
+        }
+        self.insert_inner(entity, components, loc.archetype, loc)?;
+        Ok(())
+    }
+
     /// The implementation backing [`insert`](Self::insert) exposed so that it can also be used by [`exchange`](Self::exchange).
     ///
     /// Note that `graph_origin` is always equal to `loc.archetype` during insertion. Only for exchange, `graph_origin` identifies
@@ -576,12 +1076,15 @@ impl World {
         let target_storage;
         let target = match components.key() {
             None => {
+                assert_no_duplicates(&components);
                 target_storage = self.archetypes.get_insert_target(graph_origin, &components);
                 &target_storage
             }
             Some(key) => match self.insert_edges.entry((graph_origin, key)) {
                 Entry::Occupied(entry) => entry.into_mut(),
                 Entry::Vacant(entry) => {
+                    // Cached edges are only created for valid bundles, so this needn't be repeated
+                    assert_no_duplicates(&components);
                     let target = self.archetypes.get_insert_target(graph_origin, &components);
                     entry.insert(target)
                 }
@@ -637,6 +1140,9 @@ impl World {
                 self.entities.meta[moved as usize].location.index = loc.index;
             }
         }
+        if let Some(ref mut events) = self.archetype_events {
+            events.push((entity, loc.archetype, target.index));
+        }
         Ok(())
     }
 
@@ -688,6 +1194,7 @@ impl World {
             Self::remove_target::<T>(&mut self.archetypes, &mut self.remove_edges, loc.archetype);
 
         // Store components to the target archetype and update metadata
+        let source = loc.archetype;
         if loc.archetype != target {
             // If we actually removed any components, the entity needs to be moved into a new archetype
             let (source_arch, target_arch) = index2(
@@ -708,6 +1215,9 @@ impl World {
             } {
                 self.entities.meta[moved as usize].location.index = old_index;
             }
+            if let Some(ref mut events) = self.archetype_events {
+                events.push((entity, source, target));
+            }
         }
 
         Ok(bundle)
@@ -753,6 +1263,8 @@ impl World {
         components: T,
     ) -> Result<S, ComponentError> {
         self.flush();
+        // Must precede moving `S` out, or unwinding would drop it while the archetype still owns it
+        assert_no_duplicates(&components);
 
         // Gather current metadata
         let loc = self.entities.get(entity)?;
@@ -918,6 +1430,116 @@ impl World {
         ArchetypesGeneration(self.archetypes.generation())
     }
 
+    /// Enable or disable recording of entities moving between archetypes
+    ///
+    /// While enabled, every [`insert`](Self::insert), [`remove`](Self::remove), or
+    /// [`exchange`](Self::exchange) that changes the set of components an entity has is recorded,
+    /// to be retrieved with [`drain_archetype_events`](Self::drain_archetype_events). Spawning and
+    /// despawning are not recorded. The buffer grows by one small entry per such change and is
+    /// only emptied by draining it or by [`clear_trackers`](Self::clear_trackers), one of which
+    /// should be called regularly, e.g. once per frame.
+    ///
+    /// Disabling recording discards any undrained events. Recording is disabled by default.
+    pub fn record_archetype_events(&mut self, enabled: bool) {
+        match (enabled, &self.archetype_events) {
+            (true, None) => self.archetype_events = Some(Vec::new()),
+            (false, Some(_)) => self.archetype_events = None,
+            _ => {}
+        }
+    }
+
+    /// Take the archetype changes recorded since the last call, in the order they occurred
+    ///
+    /// Yields nothing unless enabled by [`record_archetype_events`](Self::record_archetype_events).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// world.record_archetype_events(true);
+    /// let a = world.spawn((123,));
+    /// world.insert_one(a, true).unwrap();
+    /// world.exchange_one::<i32, _>(a, "abc").unwrap();
+    /// let events = world
+    ///     .drain_archetype_events()
+    ///     .map(|x| (x.entity(), x.added().collect::<Vec<_>>(), x.removed().collect::<Vec<_>>()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(events, [
+    ///     (a, vec![TypeId::of::<bool>()], vec![]),
+    ///     (a, vec![TypeId::of::<&str>()], vec![TypeId::of::<i32>()]),
+    /// ]);
+    /// assert_eq!(world.drain_archetype_events().count(), 0);
+    /// ```
+    pub fn drain_archetype_events(&mut self) -> DrainArchetypeEvents<'_> {
+        DrainArchetypeEvents {
+            archetypes: &self.archetypes.archetypes,
+            events: self.archetype_events.as_mut().map(|x| x.drain(..)),
+        }
+    }
+
+    /// Enable or disable tracking of the order in which entities are spawned
+    ///
+    /// While enabled, every entity created by any means, including
+    /// [`spawn_batch`](Self::spawn_batch) and flushing [reserved](Self::reserve_entity) entities, is
+    /// appended to a list retrieved with [`iter_spawn_order`](Self::iter_spawn_order). This costs
+    /// one [`Entity`] of memory per spawn. Entries for despawned entities are removed in bulk once they make up most of the
+    /// list, so the list stays proportional to the number of live tracked entities.
+    ///
+    /// Only entities spawned while tracking is enabled are recorded. Disabling tracking discards
+    /// the list. Tracking is disabled by default.
+    pub fn track_spawn_order(&mut self, enabled: bool) {
+        self.entities.track_spawn_order(enabled);
+    }
+
+    /// Iterate over live entities in the order they were spawned, regardless of archetype
+    ///
+    /// Yields nothing unless enabled by [`track_spawn_order`](Self::track_spawn_order). Reserved
+    /// entities are included once they're flushed, e.g. by a call to [`spawn`](Self::spawn). An
+    /// entity spawned with [`spawn_at`](Self::spawn_at) over an existing one is considered new.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.track_spawn_order(true);
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2, true));
+    /// let c = world.spawn((3,));
+    /// world.insert_one(a, "abc").unwrap();
+    /// world.despawn(b).unwrap();
+    /// assert_eq!(world.iter_spawn_order().collect::<Vec<_>>(), [a, c]);
+    /// ```
+    pub fn iter_spawn_order(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.spawn_order()
+    }
+
+    /// Register a callback to be invoked whenever a new archetype is created
+    ///
+    /// `f` is called exactly once for each archetype created after registration, immediately after
+    /// it's added. Archetypes that already exist are not reported. Useful for incrementally
+    /// maintaining per-archetype caches rather than rescanning [`archetypes`](Self::archetypes)
+    /// whenever [`archetypes_generation`](Self::archetypes_generation) changes.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+    /// let mut world = World::new();
+    /// let created = Arc::new(AtomicUsize::new(0));
+    /// let counter = created.clone();
+    /// world.on_archetype_created(move |_| {
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    /// world.spawn((123, "abc"));
+    /// world.spawn((456, "def"));
+    /// world.spawn((true,));
+    /// assert_eq!(created.load(Ordering::Relaxed), 2);
+    /// ```
+    pub fn on_archetype_created(&mut self, f: impl FnMut(&Archetype) + Send + Sync + 'static) {
+        self.archetypes.listeners.push(Box::new(f));
+    }
+
     /// Number of currently live entities
     #[inline]
     pub fn len(&self) -> u32 {
@@ -934,6 +1556,78 @@ impl World {
 unsafe impl Send for World {}
 unsafe impl Sync for World {}
 
+/// Iterator over archetype changes, returned by [`World::drain_archetype_events`]
+pub struct DrainArchetypeEvents<'a> {
+    archetypes: &'a [Archetype],
+    events: Option<vec::Drain<'a, (Entity, u32, u32)>>,
+}
+
+impl<'a> Iterator for DrainArchetypeEvents<'a> {
+    type Item = ArchetypeEvent<'a>;
+
+    fn next(&mut self) -> Option<ArchetypeEvent<'a>> {
+        let (entity, from, to) = self.events.as_mut()?.next()?;
+        Some(ArchetypeEvent {
+            entity,
+            from: &self.archetypes[from as usize],
+            to: &self.archetypes[to as usize],
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.as_ref().map_or((0, Some(0)), |x| x.size_hint())
+    }
+}
+
+impl ExactSizeIterator for DrainArchetypeEvents<'_> {}
+
+/// An entity having been moved from one [`Archetype`] to another
+#[derive(Copy, Clone)]
+pub struct ArchetypeEvent<'a> {
+    entity: Entity,
+    from: &'a Archetype,
+    to: &'a Archetype,
+}
+
+impl<'a> ArchetypeEvent<'a> {
+    /// The entity that was moved
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// The archetype the entity previously belonged to
+    pub fn from(&self) -> &'a Archetype {
+        self.from
+    }
+
+    /// The archetype the entity was moved into
+    ///
+    /// The entity may have since moved elsewhere or been despawned.
+    pub fn to(&self) -> &'a Archetype {
+        self.to
+    }
+
+    /// Component types the entity gained
+    pub fn added(&self) -> impl Iterator<Item = TypeId> + 'a {
+        let from = self.from;
+        self.to
+            .component_types()
+            .filter(move |&x| !from.has_dynamic(x))
+    }
+
+    /// Component types the entity lost
+    pub fn removed(&self) -> impl Iterator<Item = TypeId> + 'a {
+        let to = self.to;
+        self.from
+            .component_types()
+            .filter(move |&x| !to.has_dynamic(x))
+    }
+}
+
+/// Component attached to entities in a [`World::graveyard`], recording their original handle
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Dead(pub Entity);
+
 impl Default for World {
     fn default() -> Self {
         Self::new()
@@ -948,6 +1642,35 @@ impl<'a> IntoIterator for &'a World {
     }
 }
 
+/// Whether `components` contains more than one component of some type
+/// Removes the `T`s attached by [`World::with_scratch`] when dropped, even while unwinding
+struct RemoveScratch<'a, T: Component> {
+    world: &'a mut World,
+    entities: Vec<Entity>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Component> Drop for RemoveScratch<'_, T> {
+    fn drop(&mut self) {
+        for &entity in &self.entities {
+            // `body` may have despawned the entity or removed the component itself
+            let _ = self.world.remove_one::<T>(entity);
+        }
+    }
+}
+
+fn has_duplicates(components: &impl DynamicBundle) -> bool {
+    // Ids are sorted by alignment then id, so duplicates are always adjacent
+    components.with_ids(|ids| ids.windows(2).any(|x| x[0] == x[1]))
+}
+
+fn assert_no_duplicates(components: &impl DynamicBundle) {
+    assert!(
+        !has_duplicates(components),
+        "attempted to insert duplicate components; each type must occur at most once!"
+    );
+}
+
 fn index2<T>(x: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
     assert!(i != j);
     assert!(i < x.len());
@@ -990,6 +1713,34 @@ impl From<MissingComponent> for ComponentError {
     }
 }
 
+/// Errors that arise when inserting components with [`World::try_insert`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum InsertError {
+    /// The entity was already despawned
+    NoSuchEntity,
+    /// The inserted bundle contained more than one component of the same type
+    DuplicateComponent,
+}
+
+#[cfg(feature = "std")]
+impl Error for InsertError {}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use InsertError::*;
+        match *self {
+            NoSuchEntity => f.write_str("no such entity"),
+            DuplicateComponent => f.write_str("duplicate component in bundle"),
+        }
+    }
+}
+
+impl From<NoSuchEntity> for InsertError {
+    fn from(NoSuchEntity: NoSuchEntity) -> Self {
+        InsertError::NoSuchEntity
+    }
+}
+
 /// Errors that arise when querying a single entity
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum QueryOneError {
@@ -1212,14 +1963,19 @@ struct ArchetypeSet {
     /// Maps sorted component type sets to archetypes
     index: HashMap<Box<[TypeId]>, u32>,
     archetypes: Vec<Archetype>,
+    /// Callbacks registered with `World::on_archetype_created`
+    listeners: Vec<ArchetypeListener>,
 }
 
+type ArchetypeListener = Box<dyn FnMut(&Archetype) + Send + Sync>;
+
 impl ArchetypeSet {
     fn new() -> Self {
         // `flush` assumes archetype 0 always exists, representing entities with no components.
         Self {
             index: Some((Box::default(), 0)).into_iter().collect(),
             archetypes: vec![Archetype::new(Vec::new())],
+            listeners: Vec::new(),
         }
     }
 
@@ -1240,6 +1996,7 @@ impl ArchetypeSet {
         self.archetypes.push(Archetype::new(info));
         let old = self.index.insert(components, x);
         debug_assert!(old.is_none(), "inserted duplicate archetype");
+        self.notify(x);
         x
     }
 
@@ -1266,11 +2023,20 @@ impl ArchetypeSet {
                 let id = self.archetypes.len() as u32;
                 self.archetypes.push(archetype);
                 x.insert(id);
+                self.notify(id);
                 (id, 0)
             }
         }
     }
 
+    /// Inform listeners that the archetype `id` was just created
+    fn notify(&mut self, id: u32) {
+        let archetype = &self.archetypes[id as usize];
+        for listener in &mut self.listeners {
+            listener(archetype);
+        }
+    }
+
     fn generation(&self) -> u32 {
         self.archetypes.len() as u32
     }
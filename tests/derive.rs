@@ -6,7 +6,12 @@
 fn derive() {
     const TEST_DIR: &str = "tests/derive";
     let t = trybuild::TestCases::new();
-    let failures = &["enum.rs", "union.rs", "wrong_lifetime.rs"];
+    let failures = &[
+        "enum.rs",
+        "union.rs",
+        "wrong_lifetime.rs",
+        "not_transparent.rs",
+    ];
     let successes = &[
         "unit_structs.rs",
         "tuple_structs.rs",
@@ -15,6 +20,7 @@ fn derive() {
         "generics.rs",
         "nested_query.rs",
         "export.rs",
+        "transparent.rs",
     ];
     for &passing_test in successes {
         t.pass(&format!("{}/{}", TEST_DIR, passing_test));
@@ -0,0 +1,20 @@
+use hecs::Transparent;
+
+#[derive(Transparent)]
+struct Missing(f32);
+
+#[derive(Transparent)]
+#[repr(C)]
+struct WrongRepr(f32);
+
+#[derive(Transparent)]
+#[repr(transparent)]
+struct TooMany(f32, ());
+
+#[derive(Transparent)]
+#[repr(transparent)]
+enum Enum {
+    A(f32),
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use hecs::{Transparent, Unwrap, World};
+
+#[derive(Transparent)]
+#[repr(transparent)]
+struct Health(f32);
+
+#[derive(Transparent)]
+#[repr(transparent)]
+struct Named {
+    value: u32,
+}
+
+#[derive(Transparent)]
+#[repr(transparent)]
+struct Generic<T: Send + Sync + 'static>(T);
+
+fn main() {
+    let mut world = World::new();
+    let e = world.spawn((Health(1.0), Named { value: 2 }, Generic(3u8)));
+    for (_, (health, value, x)) in
+        world.query_mut::<(Unwrap<Health>, Unwrap<Named>, Unwrap<Generic<u8>>)>()
+    {
+        let _: (&mut f32, &mut u32, &mut u8) = (health, value, x);
+        *x += 1;
+    }
+    assert_eq!(world.get::<Generic<u8>>(e).unwrap().0, 4);
+}
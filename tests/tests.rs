@@ -871,3 +871,786 @@ fn take() {
     world_b.take(e2).unwrap();
     assert!(!world_b.contains(e2));
 }
+
+#[test]
+fn for_each_archetype() {
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    let b = world.spawn((2, "def"));
+    let c = world.spawn((3, true));
+    world.spawn(("ghi",));
+    let mut visits = 0;
+    world.query::<&mut i32>().for_each_archetype(
+        || None,
+        |scratch, batch| {
+            visits += 1;
+            let mut sum = 0;
+            for (slot, (_, x)) in scratch.iter_mut().zip(batch) {
+                assert_eq!(*slot, None);
+                sum += *x;
+                *x = sum;
+                *slot = Some(sum);
+            }
+        },
+    );
+    assert_eq!(visits, 2);
+    assert_eq!(*world.get::<i32>(a).unwrap(), 1);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 3);
+    assert_eq!(*world.get::<i32>(c).unwrap(), 3);
+}
+
+#[test]
+#[cfg_attr(not(debug_assertions), ignore)]
+#[should_panic(expected = "archetype reallocated while a component was borrowed")]
+fn realloc_while_borrowed() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    let mut query = world.query::<&mut i32>();
+    query.iter().for_each(|_| {});
+    // Leak the borrow so it's never released, then force the archetype to grow
+    core::mem::forget(query);
+    for i in 0..64 {
+        world.spawn((i, false));
+    }
+}
+
+#[test]
+fn archetype_created_hook() {
+    use std::any::TypeId;
+    use std::sync::{Arc, Mutex};
+
+    let mut world = World::new();
+    world.spawn((1,));
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    world.on_archetype_created(move |archetype| {
+        let mut types = archetype.component_types().collect::<Vec<_>>();
+        types.sort();
+        sink.lock().unwrap().push(types);
+    });
+    let e = world.spawn((2,));
+    world.insert_one(e, true).unwrap();
+    world.remove_one::<i32>(e).unwrap();
+    world.spawn((3, true));
+    let mut batch = ColumnBatchType::new();
+    batch.add::<&str>();
+    let mut batch = batch.into_batch(1);
+    batch.writer::<&str>().unwrap().push("abc").unwrap();
+    world.spawn_column_batch(batch.build().unwrap());
+
+    let sorted = |mut x: Vec<TypeId>| {
+        x.sort();
+        x
+    };
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [
+            sorted(vec![TypeId::of::<i32>(), TypeId::of::<bool>()]),
+            vec![TypeId::of::<bool>()],
+            vec![TypeId::of::<&str>()],
+        ]
+    );
+}
+
+#[test]
+fn soft_despawn_recycled_id() {
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    world.soft_despawn(a).unwrap();
+    let b = world.spawn((2,));
+    assert_eq!(a.id(), b.id());
+    world.soft_despawn(b).unwrap();
+    assert_eq!(world.soft_despawn(b), Err(NoSuchEntity));
+    assert!(world.is_empty());
+
+    let graveyard = world.graveyard().unwrap();
+    let mut corpses = graveyard
+        .query::<(&Dead, &i32)>()
+        .iter()
+        .map(|(_, (dead, &x))| (dead.0, x))
+        .collect::<Vec<_>>();
+    corpses.sort_by_key(|&(_, x)| x);
+    assert_eq!(corpses, [(a, 1), (b, 2)]);
+    assert_eq!(*graveyard.query::<&&str>().iter().next().unwrap().1, "abc");
+
+    world.clear_trackers();
+    assert!(world.graveyard().unwrap().is_empty());
+}
+
+#[test]
+fn soft_despawn_graveyard_lifecycle() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    world.despawn(a).unwrap();
+    assert_eq!(world.soft_despawn(a), Err(NoSuchEntity));
+    assert!(world.graveyard().is_none());
+
+    let b = world.spawn((2, Dead(a)));
+    world.soft_despawn(b).unwrap();
+    let graveyard = world.graveyard().unwrap();
+    // A stale `Dead` on the live entity is replaced by one naming the entity itself
+    let corpses = graveyard
+        .query::<(&Dead, &i32)>()
+        .iter()
+        .map(|(_, (dead, &x))| (dead.0, x))
+        .collect::<Vec<_>>();
+    assert_eq!(corpses, [(b, 2)]);
+
+    world.spawn((3,));
+    world.clear();
+    assert!(world.graveyard().unwrap().is_empty());
+}
+
+#[test]
+fn store_query_borrow() {
+    // Queries and their iterators are named types, so they can be stored and passed around
+    struct Positions<'w> {
+        query: QueryBorrow<'w, (&'w i32, Option<&'w bool>)>,
+    }
+
+    fn sum(iter: QueryIter<'_, (&i32, Option<&bool>)>) -> i32 {
+        iter.map(|(_, (&x, _))| x).sum()
+    }
+
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2,));
+    let mut positions = Positions {
+        query: world.query(),
+    };
+    assert_eq!(sum(positions.query.iter()), 3);
+    assert_eq!(sum(positions.query.iter()), 3);
+}
+
+#[test]
+fn iter_batched_aligned() {
+    let mut world = World::new();
+    world
+        .spawn_batch((0..1000).map(|i| (i as u64, true)))
+        .for_each(|_| {});
+    world
+        .spawn_batch((0..10).map(|i| (i as u64,)))
+        .for_each(|_| {});
+    let mut query = world.query::<(&u64, &bool)>();
+    let mut seen = Vec::new();
+    for (i, batch) in query.iter_batched_aligned(20).enumerate() {
+        let mut len = 0;
+        for (n, (_, (x, _))) in batch.enumerate() {
+            if i > 0 && n == 0 {
+                assert_eq!(x as *const u64 as usize % 64, 0);
+            }
+            seen.push(*x);
+            len += 1;
+        }
+        if i > 0 {
+            assert!(len <= 24);
+        }
+    }
+    seen.sort_unstable();
+    assert_eq!(seen, (0..1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn iter_batched_huge() {
+    let mut world = World::new();
+    world
+        .spawn_batch((0..100).map(|i| (i as u64, true)))
+        .for_each(|_| {});
+    world.spawn((7u64,));
+    for &size in &[u32::MAX, u32::MAX / 2 + 1] {
+        let mut query = world.query::<&u64>();
+        assert_eq!(query.iter_batched(size).count(), 2);
+        assert_eq!(query.iter_batched(size).flatten().count(), 101);
+        assert_eq!(query.iter_batched_aligned(size).flatten().count(), 101);
+    }
+}
+
+#[test]
+fn filter_fn() {
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    world.spawn((2, "def"));
+    let c = world.spawn((3, true, "ghi"));
+    world.spawn((4,));
+    world.spawn(("jkl",));
+    let mut query = world.query::<(&mut i32, Option<&&str>)>();
+    let odd = query
+        .filter_fn(|x: &i32| x % 2 == 1)
+        .map(|(e, (x, s))| {
+            *x *= 10;
+            (e, s.copied())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(odd.len(), 2);
+    assert!(odd.contains(&(a, Some("abc"))));
+    assert!(odd.contains(&(c, Some("ghi"))));
+    drop(query);
+    assert_eq!(*world.get::<i32>(c).unwrap(), 30);
+    // Entities lacking the filtered component are skipped
+    let strs = world
+        .query::<Option<&&str>>()
+        .filter_fn(|_: &&str| true)
+        .count();
+    assert_eq!(strs, 4);
+}
+
+#[test]
+#[should_panic(expected = "filter_fn predicate must read a component borrowed by the query")]
+fn filter_fn_unborrowed() {
+    let world = World::new();
+    world
+        .query::<&i32>()
+        .filter_fn(|_: &bool| true)
+        .for_each(|_| {});
+}
+
+#[test]
+fn archetype_events() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    world.remove_one::<bool>(a).unwrap();
+    assert_eq!(world.drain_archetype_events().count(), 0);
+
+    world.record_archetype_events(true);
+    world.insert_one(a, 2).unwrap(); // Overwrite only, no move
+    world.insert_one(a, "abc").unwrap();
+    world.remove::<(i32, &str)>(a).unwrap();
+    let events = world
+        .drain_archetype_events()
+        .map(|x| {
+            assert_eq!(x.entity(), a);
+            (x.added().collect::<Vec<_>>(), x.removed().count())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(events, [(vec![TypeId::of::<&str>()], 0), (vec![], 2)]);
+
+    world.insert_one(a, 3).unwrap();
+    world.record_archetype_events(false);
+    assert_eq!(world.drain_archetype_events().count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "attempted to insert duplicate components")]
+fn insert_duplicate() {
+    let mut world = World::new();
+    let e = world.spawn(("abc".to_string(),));
+    world
+        .insert(e, ("def".to_string(), "ghi".to_string()))
+        .unwrap();
+}
+
+/// Counts how many times values sharing a counter have been dropped
+struct DropCounter(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// A runtime-built bundle that, unlike `EntityBuilder`, doesn't deduplicate its components
+struct DuplicateBundle(Vec<DropCounter>);
+
+unsafe impl DynamicBundle for DuplicateBundle {
+    fn with_ids<T>(&self, f: impl FnOnce(&[std::any::TypeId]) -> T) -> T {
+        f(&vec![std::any::TypeId::of::<DropCounter>(); self.0.len()])
+    }
+
+    fn type_info(&self) -> Vec<TypeInfo> {
+        vec![TypeInfo::of::<DropCounter>(); self.0.len()]
+    }
+
+    unsafe fn put(self, mut f: impl FnMut(*mut u8, TypeInfo)) {
+        for mut x in self.0 {
+            f(
+                (&mut x as *mut DropCounter).cast(),
+                TypeInfo::of::<DropCounter>(),
+            );
+            std::mem::forget(x);
+        }
+    }
+}
+
+#[test]
+fn try_insert_dynamic_duplicate() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let e = world.spawn((1,));
+    let bundle = DuplicateBundle(vec![DropCounter(drops.clone()), DropCounter(drops.clone())]);
+    assert_eq!(
+        world.try_insert(e, bundle),
+        Err(InsertError::DuplicateComponent)
+    );
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+    let entity = world.entity(e).unwrap();
+    assert_eq!(
+        entity.component_types().collect::<Vec<_>>(),
+        [std::any::TypeId::of::<i32>()]
+    );
+    assert_eq!(*world.get::<i32>(e).unwrap(), 1);
+    drop(world);
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+
+    let mut world = World::new();
+    let e = world.spawn((1,));
+    let mut builder = EntityBuilder::new();
+    builder.add("abc").add(true);
+    world.try_insert(e, builder.build()).unwrap();
+    assert_eq!(*world.get::<&str>(e).unwrap(), "abc");
+    world.despawn(e).unwrap();
+    assert_eq!(world.try_insert(e, (2,)), Err(InsertError::NoSuchEntity));
+}
+
+#[test]
+fn exchange_duplicate() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let e = world.spawn((DropCounter(drops.clone()),));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        world.exchange::<(DropCounter,), _>(e, (1u32, 2u32))
+    }));
+    assert!(result.is_err());
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    assert!(world.get::<DropCounter>(e).is_ok());
+    drop(world);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn replace_all() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let old = Arc::new(AtomicUsize::new(0));
+    let new = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let a = world.spawn((DropCounter(old.clone()), 1));
+    let b = world.spawn((DropCounter(old.clone()), true));
+    let c = world.spawn((DropCounter(old.clone()), true));
+    let d = world.spawn((4, "untouched"));
+    world.replace_all::<DropCounter>(|x| *x = DropCounter(new.clone()));
+    assert_eq!(old.load(Ordering::Relaxed), 3);
+    assert_eq!(new.load(Ordering::Relaxed), 0);
+    for &e in &[a, b, c] {
+        assert!(Arc::ptr_eq(&world.get::<DropCounter>(e).unwrap().0, &new));
+    }
+    assert!(!world.entity(d).unwrap().has::<DropCounter>());
+    assert_eq!(*world.get::<i32>(d).unwrap(), 4);
+    assert_eq!(*world.get::<&str>(d).unwrap(), "untouched");
+    drop(world);
+    assert_eq!(old.load(Ordering::Relaxed), 3);
+    assert_eq!(new.load(Ordering::Relaxed), 3);
+
+    struct Marker;
+    let mut world = World::new();
+    world.spawn((Marker,));
+    world.spawn((Marker, 1));
+    world.spawn((Marker, 2));
+    world.spawn((3,));
+    let mut visited = 0;
+    world.replace_all::<Marker>(|_| visited += 1);
+    assert_eq!(visited, 3);
+}
+
+#[test]
+fn query_dynamic() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    let a = world.spawn((1u16, true));
+    let b = world.spawn((2u16, "abc"));
+    world.spawn(("def",));
+    world.spawn(((),));
+    let mut query = world.query_dynamic(TypeId::of::<u16>());
+    let mut found = query
+        .iter()
+        .map(|(e, bytes)| {
+            assert_eq!(bytes.len(), 2);
+            (e, unsafe { bytes.as_ptr().cast::<u16>().read_unaligned() })
+        })
+        .collect::<Vec<_>>();
+    found.sort();
+    assert_eq!(found, [(a, 1), (b, 2)]);
+    // Shared borrows coexist
+    assert_eq!(*world.get::<u16>(a).unwrap(), 1);
+    drop(query);
+    assert_eq!(world.query_dynamic(TypeId::of::<()>()).iter().count(), 1);
+    *world.get_mut::<u16>(a).unwrap() = 3;
+}
+
+#[test]
+#[should_panic(expected = "already borrowed uniquely")]
+fn query_dynamic_borrow_conflict() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    let a = world.spawn((1u16,));
+    let _guard = world.get_mut::<u16>(a).unwrap();
+    world.query_dynamic(TypeId::of::<u16>());
+}
+
+#[test]
+fn query_fold() {
+    let mut world = World::new();
+    for i in 0..10 {
+        world.spawn((i,));
+        world.spawn((i, true));
+    }
+    world.spawn((true,));
+    let mut query = world.query::<&i32>();
+    let mut iter = query.iter();
+    let first = *iter.next().unwrap().1;
+    let rest = iter.fold(0, |acc, (_, &x)| acc + x);
+    assert_eq!(first + rest, 90);
+    drop(query);
+    let mut entities = Vec::new();
+    world
+        .query_mut::<&mut i32>()
+        .into_iter()
+        .for_each(|(e, x)| {
+            *x += 1;
+            entities.push(e);
+        });
+    assert_eq!(entities.len(), 20);
+    for e in entities {
+        assert!(world.contains(e));
+    }
+    assert_eq!(
+        world.query::<&i32>().iter().map(|(_, &x)| x).sum::<i32>(),
+        110
+    );
+}
+
+#[test]
+fn with_scratch() {
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    let b = world.spawn((2,));
+    let c = world.spawn((3, true));
+    let d = world.spawn(("def",));
+    let seen = world.with_scratch::<&i32, _, _>(
+        |_, &x| x > 1,
+        |world| {
+            world.despawn(b).unwrap();
+            let mut seen = world
+                .query::<&bool>()
+                .iter()
+                .map(|(e, &x)| (e, x))
+                .collect::<Vec<_>>();
+            seen.sort();
+            seen
+        },
+    );
+    // `c` already had a `bool`, so it's neither overwritten nor removed
+    assert_eq!(seen, [(a, false), (c, true)]);
+    assert!(!world.entity(a).unwrap().has::<bool>());
+    assert!(*world.get::<bool>(c).unwrap());
+    assert!(!world.entity(d).unwrap().has::<bool>());
+}
+
+#[test]
+fn with_scratch_whole_archetypes() {
+    let mut world = World::new();
+    // Already in the archetype the others will move to
+    let existing = world.spawn((0, "existing", 0u64));
+    let entities = (1..=10)
+        .map(|i| world.spawn((i, "moved")))
+        .collect::<Vec<_>>();
+    let zst = world.spawn(((), 11));
+    world.record_archetype_events(true);
+    let seen = world.with_scratch::<&i32, _, _>(
+        |_, &x| x as u64 * 10,
+        |world| {
+            assert_eq!(world.drain_archetype_events().count(), 11);
+            let mut seen = world
+                .query::<(&i32, &u64)>()
+                .iter()
+                .map(|(e, (&i, &x))| (e, i, x))
+                .collect::<Vec<_>>();
+            seen.sort();
+            seen
+        },
+    );
+    let mut expected = entities
+        .iter()
+        .enumerate()
+        .map(|(i, &e)| (e, i as i32 + 1, (i as u64 + 1) * 10))
+        .collect::<Vec<_>>();
+    expected.push((existing, 0, 0));
+    expected.push((zst, 11, 110));
+    expected.sort();
+    assert_eq!(seen, expected);
+    for (i, &e) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(e).unwrap(), i as i32 + 1);
+        assert_eq!(*world.get::<&str>(e).unwrap(), "moved");
+        assert!(!world.entity(e).unwrap().has::<u64>());
+    }
+    assert!(world.entity(zst).unwrap().has::<()>());
+    assert_eq!(*world.get::<u64>(existing).unwrap(), 0);
+}
+
+#[test]
+fn with_scratch_panic() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        world.with_scratch::<&i32, _, _>(|_, _| true, |_| panic!("body"));
+    }));
+    assert!(result.is_err());
+    assert!(!world.entity(a).unwrap().has::<bool>());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_shared_queries() {
+    let mut world = World::new();
+    for i in 0..1_000 {
+        world.spawn((i, i as f32));
+        world.spawn((i,));
+    }
+    let world = std::sync::Arc::new(world);
+    let expected = (0..1_000).sum::<i32>() * 2;
+    let threads = (0..8)
+        .map(|_| {
+            let world = world.clone();
+            let mut query = PreparedQuery::<&i32>::new();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let sum = world.query::<&i32>().iter().map(|(_, &x)| x).sum::<i32>();
+                    assert_eq!(sum, expected);
+                    let sum = query.query(&world).iter().map(|(_, &x)| x).sum::<i32>();
+                    assert_eq!(sum, expected);
+                    assert_eq!(world.query::<(&i32, &f32)>().iter().count(), 1_000);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[should_panic(expected = "worker")]
+fn scoped_for_each_propagates_panics() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2,));
+    world.query::<&i32>().scoped_for_each(2, |_, &x| {
+        if x == 2 {
+            panic!("worker");
+        }
+    });
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[cfg_attr(miri, ignore)]
+fn scoped_for_each_assignment() {
+    use std::sync::Mutex;
+
+    let mut world = World::new();
+    world.spawn((0, 'a'));
+    let empty = world.spawn((1, "empty"));
+    world.despawn(empty).unwrap();
+    world.spawn((2, true));
+    world.spawn((3, 0u8));
+    let workers = Mutex::new(Vec::new());
+    world.query::<&i32>().scoped_for_each(2, |_, &x| {
+        workers
+            .lock()
+            .unwrap()
+            .push((x, std::thread::current().id()));
+    });
+    let mut workers = workers.into_inner().unwrap();
+    workers.sort_by_key(|&(x, _)| x);
+    let threads = workers.iter().map(|&(_, id)| id).collect::<Vec<_>>();
+    // The empty archetype doesn't take a slot, so the first and third non-empty ones share a worker
+    assert_eq!(threads.len(), 3);
+    assert_eq!(threads[0], threads[2]);
+    assert_ne!(threads[0], threads[1]);
+}
+
+#[test]
+fn explain_filters() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    world.spawn((1,));
+    world.spawn((2, true));
+    world.spawn((3, 'a'));
+    world.spawn((4, true, 'a'));
+    let explanations = world
+        .query::<(&i32, With<bool, ()>, Without<char, ()>)>()
+        .explain();
+    let find = |bool_: bool, char_: bool| {
+        explanations
+            .iter()
+            .find(|x| {
+                x.archetype.has::<i32>()
+                    && x.archetype.has::<bool>() == bool_
+                    && x.archetype.has::<char>() == char_
+            })
+            .unwrap()
+    };
+
+    let lone = find(false, false);
+    assert!(!lone.matched);
+    assert!(lone.missing.is_empty());
+    assert_eq!(lone.required, [TypeId::of::<bool>()]);
+    assert!(lone.excluded.is_empty());
+
+    let matching = find(true, false);
+    assert!(matching.matched);
+    assert!(matching.required.is_empty());
+    assert!(matching.excluded.is_empty());
+
+    let with_char = find(false, true);
+    assert!(!with_char.matched);
+    assert_eq!(with_char.required, [TypeId::of::<bool>()]);
+    assert_eq!(with_char.excluded, [TypeId::of::<char>()]);
+
+    let both = find(true, true);
+    assert!(!both.matched);
+    assert!(both.required.is_empty());
+    assert_eq!(both.excluded, [TypeId::of::<char>()]);
+}
+
+#[test]
+fn nested_disjoint_queries() {
+    struct Camera(i32);
+    struct Renderable(i32);
+
+    let mut world = World::new();
+    world.spawn((Camera(1),));
+    world.spawn((Camera(10),));
+    let a = world.spawn((Renderable(0),));
+    let b = world.spawn((Renderable(0), true));
+    for (_, camera) in world.query::<&Camera>().iter() {
+        for (_, renderable) in world.query::<&mut Renderable>().iter() {
+            renderable.0 += camera.0;
+        }
+    }
+    assert_eq!(world.get::<Renderable>(a).unwrap().0, 11);
+    assert_eq!(world.get::<Renderable>(b).unwrap().0, 11);
+}
+
+#[test]
+fn command_buffer_between_queries() {
+    let mut world = World::new();
+    let entities = world
+        .spawn_batch((0..100).map(|i| (i,)))
+        .collect::<Vec<_>>();
+    let mut cmd = CommandBuffer::new();
+    for round in 0..3 {
+        let mut visited = Vec::new();
+        for (entity, &i) in world.query::<&i32>().iter() {
+            visited.push(entity);
+            // Move half of the entities between archetypes each round
+            if i % 2 == 0 {
+                if round % 2 == 0 {
+                    cmd.insert(entity, (true,));
+                } else {
+                    cmd.remove::<(bool,)>(entity);
+                }
+            }
+        }
+        visited.sort();
+        visited.dedup();
+        assert_eq!(visited.len(), entities.len());
+        cmd.run_on(&mut world);
+        assert_eq!(
+            world.query::<(&i32, &bool)>().iter().count(),
+            if round % 2 == 0 { 50 } else { 0 }
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "i32 is still borrowed"))]
+#[cfg_attr(
+    not(debug_assertions),
+    should_panic(expected = "a component is still borrowed")
+)]
+fn leaked_borrow() {
+    let mut world = World::new();
+    let a = world.spawn((42, true));
+    std::mem::forget(world.get::<i32>(a).unwrap());
+    world.assert_no_borrows();
+}
+
+#[test]
+fn deterministic_entity_reuse() {
+    fn run() -> Vec<Entity> {
+        let mut world = World::new();
+        let mut spawned = (0..10).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+        for &i in &[3, 7, 1] {
+            world.despawn(spawned[i]).unwrap();
+        }
+        for i in 0..5 {
+            spawned.push(world.spawn((i,)));
+        }
+        spawned.push(world.reserve_entity());
+        spawned
+    }
+
+    let first = run();
+    assert_eq!(first, run());
+    // Freed IDs are reused most recently despawned first
+    assert_eq!(first[10].id(), first[1].id());
+    assert_eq!(first[11].id(), first[7].id());
+    assert_eq!(first[12].id(), first[3].id());
+    assert_eq!(first[13].id(), 10);
+}
+
+#[test]
+fn spawn_order() {
+    let mut world = World::new();
+    let untracked = world.spawn((0,));
+    world.track_spawn_order(true);
+    let mut expected = Vec::new();
+    for i in 0..10 {
+        expected.push(world.spawn((i,)));
+    }
+    expected.extend(world.spawn_batch((0..5).map(|i| (i, true))));
+    let reserved = world.reserve_entity();
+    expected.push(world.spawn((true,)));
+    expected.insert(expected.len() - 1, reserved);
+    for &e in &expected[1..8] {
+        world.despawn(e).unwrap();
+    }
+    expected.drain(1..8);
+    assert_eq!(world.iter_spawn_order().collect::<Vec<_>>(), expected);
+    let respawned = world.spawn(("abc",));
+    expected.push(respawned);
+    assert_eq!(world.iter_spawn_order().collect::<Vec<_>>(), expected);
+    world.despawn(untracked).unwrap();
+    world.track_spawn_order(false);
+    assert_eq!(world.iter_spawn_order().count(), 0);
+}
+
+#[test]
+fn spawn_order_restore() {
+    let mut world = World::new();
+    let untracked = world.spawn((0,));
+    world.track_spawn_order(true);
+    let a = world.spawn((1,));
+    let b = world.spawn((2,));
+    // Freeing an untracked entity mustn't hide tracked ones
+    world.despawn(untracked).unwrap();
+    world.despawn(a).unwrap();
+    world.spawn_at(a, (3,));
+    assert_eq!(world.iter_spawn_order().collect::<Vec<_>>(), [b, a]);
+    world.spawn_at(b, (4,));
+    assert_eq!(world.iter_spawn_order().collect::<Vec<_>>(), [a, b]);
+    assert_eq!(world.len(), 2);
+}